@@ -0,0 +1,223 @@
+use std::future::Future;
+
+use chrono::Utc;
+
+use crate::{
+    errors::CandlesError,
+    types::{Candle, CandlesRequest},
+};
+
+/// Which end of the requested range an exchange fills a page from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageDirection {
+    /// Pages start at the requested start time (Binance style), so the cursor walks forward.
+    Forward,
+    /// Pages end at the requested end time (OKX style), so the cursor walks backward.
+    Backward,
+}
+
+/// Walks an arbitrary range page by page and returns one contiguous, ascending, deduplicated series.
+///
+/// `fetch` is a single-page request, normally a connection's `get_page`. The request must carry a
+/// start time; a missing end time means "up to now".
+pub async fn backfill<F, Fut>(request: CandlesRequest, direction: PageDirection, fetch: F) -> Result<Vec<Candle>, CandlesError>
+where
+    F: Fn(CandlesRequest) -> Fut,
+    Fut: Future<Output = Result<Vec<Candle>, CandlesError>>,
+{
+    let start = request.start.ok_or_else(|| CandlesError::InvalidRequest {
+        reason: "Backfill requires a start time".to_string(),
+    })?;
+    let end = request.end.unwrap_or_else(|| Utc::now().timestamp_millis());
+
+    if start > end {
+        return Err(CandlesError::InvalidRequest {
+            reason: format!("Backfill start {start} is after end {end}"),
+        });
+    }
+
+    let mut candles = Vec::new();
+
+    match direction {
+        PageDirection::Forward => {
+            let mut cursor = start;
+
+            loop {
+                let page = fetch(request.clone().with_start(cursor).with_end(end)).await?;
+                let page: Vec<Candle> = page.into_iter().filter(|c| c.timestamp >= cursor && c.timestamp <= end).collect();
+
+                let Some(last) = page.last() else { break };
+                let next = last.timestamp + 1;
+
+                candles.extend(page);
+
+                if next > end {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+        PageDirection::Backward => {
+            let mut cursor = end;
+
+            loop {
+                let page = fetch(request.clone().with_start(start).with_end(cursor)).await?;
+                let page: Vec<Candle> = page.into_iter().filter(|c| c.timestamp >= start && c.timestamp <= cursor).collect();
+
+                let Some(first) = page.first() else { break };
+                let next = first.timestamp - 1;
+
+                candles.extend(page);
+
+                if next < start {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+    }
+
+    candles.sort_by_key(|c| c.timestamp);
+    candles.dedup_by_key(|c| c.timestamp);
+
+    Ok(candles)
+}
+
+/// Collects the most recent `limit` candles of the requested range by walking pages backward from its end.
+///
+/// Page requests only carry an end bound, which every exchange answers with the latest page before it; the start bound
+/// is applied locally.
+pub async fn latest<F, Fut>(request: CandlesRequest, limit: usize, page_size: usize, fetch: F) -> Result<Vec<Candle>, CandlesError>
+where
+    F: Fn(CandlesRequest) -> Fut,
+    Fut: Future<Output = Result<Vec<Candle>, CandlesError>>,
+{
+    let mut candles: Vec<Candle> = Vec::with_capacity(limit);
+    let mut cursor = request.end;
+
+    while candles.len() < limit {
+        let mut page_request = request.clone().with_limit(page_size);
+        page_request.start = None;
+        page_request.end = cursor;
+
+        let page = fetch(page_request).await?;
+        let page: Vec<Candle> = page
+            .into_iter()
+            .filter(|c| cursor.is_none_or(|end| c.timestamp <= end) && request.contains(c.timestamp))
+            .collect();
+
+        let Some(first) = page.first() else { break };
+        let next = first.timestamp - 1;
+
+        candles.extend(page);
+
+        if request.start.is_some_and(|start| next < start) {
+            break;
+        }
+        cursor = Some(next);
+    }
+
+    candles.sort_by_key(|c| c.timestamp);
+    candles.dedup_by_key(|c| c.timestamp);
+    candles.drain(..candles.len().saturating_sub(limit));
+
+    Ok(candles)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        backfill::{PageDirection, backfill, latest},
+        connections::Connection,
+        errors::CandlesError,
+        types::{Candle, CandlesRequest, Instrument, MarketType, Timeframe},
+    };
+
+    const HOUR: i64 = 3_600_000;
+    const PAGE_SIZE: usize = 4;
+
+    fn request(start: i64, end: i64) -> CandlesRequest {
+        CandlesRequest::new(Instrument {
+            asset_id: "bitcoin".to_owned(),
+            pair: "BTCUSDT".to_owned(),
+            connection: Connection::Binance,
+            market_type: MarketType::Spot,
+            timeframe: Timeframe::H1,
+        })
+        .with_start(start)
+        .with_end(end)
+    }
+
+    fn candle(timestamp: i64) -> Candle {
+        Candle { timestamp, ..Default::default() }
+    }
+
+    /// An exchange holding one candle per hour for hours 0..30, serving at most `PAGE_SIZE` per call.
+    /// Pages overlap the cursor by one candle to exercise deduplication.
+    async fn fake_page(request: CandlesRequest, direction: PageDirection) -> Result<Vec<Candle>, CandlesError> {
+        let start = request.start.unwrap() - HOUR;
+        let end = request.end.unwrap() + HOUR;
+        let in_range: Vec<Candle> = (0..30).map(|h| candle(h * HOUR)).filter(|c| c.timestamp >= start && c.timestamp <= end).collect();
+
+        Ok(match direction {
+            PageDirection::Forward => in_range.into_iter().take(PAGE_SIZE).collect(),
+            PageDirection::Backward => in_range[in_range.len().saturating_sub(PAGE_SIZE)..].to_vec(),
+        })
+    }
+
+    async fn assert_contiguous(direction: PageDirection) {
+        let candles = backfill(request(3 * HOUR, 21 * HOUR), direction, |r| fake_page(r, direction)).await.unwrap();
+
+        let timestamps: Vec<i64> = candles.iter().map(|c| c.timestamp).collect();
+        let expected: Vec<i64> = (3..=21).map(|h| h * HOUR).collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[tokio::test]
+    async fn test_forward_backfill() {
+        assert_contiguous(PageDirection::Forward).await;
+    }
+
+    #[tokio::test]
+    async fn test_backward_backfill() {
+        assert_contiguous(PageDirection::Backward).await;
+    }
+
+    #[tokio::test]
+    async fn test_latest_spans_pages() {
+        let mut request = request(0, 0);
+        request.start = None;
+        request.end = None;
+
+        let page = |r: CandlesRequest| async move {
+            let end = r.end.unwrap_or(i64::MAX);
+            let in_range: Vec<Candle> = (0..30).map(|h| candle(h * HOUR)).filter(|c| c.timestamp <= end).collect();
+            Ok::<_, CandlesError>(in_range[in_range.len().saturating_sub(PAGE_SIZE)..].to_vec())
+        };
+
+        let candles = latest(request.clone(), 10, PAGE_SIZE, page).await.unwrap();
+        let timestamps: Vec<i64> = candles.iter().map(|c| c.timestamp).collect();
+        assert_eq!(timestamps, (20..30).map(|h| h * HOUR).collect::<Vec<_>>());
+
+        // The start bound stops the walk early
+        let candles = latest(request.with_start(25 * HOUR), 10, PAGE_SIZE, page).await.unwrap();
+        assert_eq!(candles.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_requires_start() {
+        let mut request = request(0, HOUR);
+        request.start = None;
+
+        let result = backfill(request, PageDirection::Forward, |r| fake_page(r, PageDirection::Forward)).await;
+
+        assert!(matches!(result, Err(CandlesError::InvalidRequest { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_rejects_reversed_range() {
+        let result = backfill(request(2 * HOUR, HOUR), PageDirection::Forward, |r| fake_page(r, PageDirection::Forward)).await;
+
+        assert!(matches!(result, Err(CandlesError::InvalidRequest { reason }) if reason.contains("after end")));
+    }
+}
@@ -1,11 +1,155 @@
 use async_trait::async_trait;
 
 use crate::{
+    backfill::{PageDirection, backfill, latest},
+    capabilities::Capabilities,
     errors::CandlesError,
-    types::{Candle, Instrument},
+    resample::resampled,
+    series::CandleSeries,
+    types::{Candle, CandlesRequest, MarketType},
+    utils::validate_timestamps,
 };
 
+/// A candles source. Object safe, so it can be shared as `Arc<dyn ExchangeClient>` and replaced by custom implementations.
+///
+/// Implementations provide single exchange requests through [`ExchangeClient::get_page`]; fetching more than one page is
+/// handled by the provided methods.
 #[async_trait]
-pub trait BaseConnection {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, CandlesError>;
+pub trait ExchangeClient: Send + Sync {
+    /// One request to the exchange, returning at most [`ExchangeClient::max_page_size`] candles in ascending order.
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError>;
+
+    /// The most candles the exchange returns for one request like `request`.
+    fn max_page_size(&self, request: &CandlesRequest) -> usize;
+
+    /// Which end of a ranged request the exchange fills a page from, used by backfill.
+    fn page_direction(&self, market_type: &MarketType) -> PageDirection;
+
+    /// What the exchange serves for `market_type`, for planning requests up front. `None` when it has no such market.
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities>;
+
+    /// Fetches candles, paginating transparently when `request.limit` exceeds one page.
+    ///
+    /// With a limit the most recent `limit` candles of the requested range are returned. Timeframes the exchange does
+    /// not serve are resampled from a finer one, see [`resampled`].
+    async fn get_candles(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        resampled(request, |request| async move {
+            let page_size = self.max_page_size(&request);
+
+            match request.limit {
+                // Exchanges fill a ranged page from either end, so a limited range is walked back from its end instead, with
+                // one spare candle for the forming one that `closed_only` drops
+                Some(limit) if limit > page_size || request.start.is_some() => {
+                    let page_size = (limit + request.closed_only as usize).min(page_size);
+
+                    latest(request, limit, page_size, |page| self.get_checked_page(page)).await
+                }
+                limit => {
+                    // One spare candle stands in for the forming one that `closed_only` drops
+                    let request = match limit {
+                        Some(limit) if request.closed_only && limit < page_size => request.with_limit(limit + 1),
+                        _ => request,
+                    };
+                    let mut candles = self.get_checked_page(request).await?;
+
+                    if let Some(limit) = limit {
+                        candles.drain(..candles.len().saturating_sub(limit));
+                    }
+
+                    Ok(candles)
+                }
+            }
+        })
+        .await
+    }
+
+    /// [`ExchangeClient::get_candles`] as columns.
+    async fn get_series(&self, request: CandlesRequest) -> Result<CandleSeries, CandlesError> {
+        let instrument = request.instrument.clone();
+
+        Ok(CandleSeries::from_candles(instrument, self.get_candles(request).await?))
+    }
+
+    /// Fetches an arbitrary range by walking pages, see [`backfill`].
+    async fn backfill(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        resampled(request, |request| async move {
+            let direction = self.page_direction(&request.instrument.market_type);
+            let page_size = self.max_page_size(&request);
+
+            backfill(request.with_limit(page_size), direction, |page| self.get_checked_page(page)).await
+        })
+        .await
+    }
+
+    /// [`ExchangeClient::get_page`] with the millisecond timestamp contract enforced and `closed_only` applied.
+    async fn get_checked_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let closed_only = request.closed_only;
+        let mut candles = self.get_page(request).await?;
+        validate_timestamps(&candles)?;
+
+        if closed_only {
+            candles.retain(|c| c.is_closed);
+        }
+
+        Ok(candles)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use crate::{
+        backfill::PageDirection,
+        base::ExchangeClient,
+        capabilities::Capabilities,
+        connections::Connection,
+        errors::CandlesError,
+        testing::instrument,
+        types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    /// A custom source that reports open times in seconds
+    struct SecondsClient;
+
+    #[async_trait]
+    impl ExchangeClient for SecondsClient {
+        async fn get_page(&self, _request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+            Ok(vec![Candle {
+                timestamp: 1710000000,
+                ..Default::default()
+            }])
+        }
+
+        fn max_page_size(&self, _request: &CandlesRequest) -> usize {
+            100
+        }
+
+        fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+            PageDirection::Forward
+        }
+
+        fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+            Some(Capabilities {
+                market_type: market_type.clone(),
+                timeframes: vec![Timeframe::H1],
+                max_page_size: 100,
+                max_history: None,
+                price_source_max_history: None,
+                extended_fields: vec![],
+                price_sources: vec![PriceSource::Last],
+                page_direction: PageDirection::Forward,
+                market_in_symbol: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_millisecond_timestamps() {
+        let client: Box<dyn ExchangeClient> = Box::new(SecondsClient);
+
+        let result = client.get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into()).await;
+
+        assert!(matches!(result, Err(CandlesError::Parse { field, index: 0, .. }) if field == "timestamp"));
+    }
 }
@@ -1,8 +1,320 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock, ResponseTemplate,
+        matchers::{path, query_param, query_param_is_missing},
+    };
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
+        binance::main::Binance,
+        connections::Connection,
+        errors::CandlesError,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, fixture, float, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/api/v3/klines")).and(query_param("symbol", "BTCUSDT")).and(query_param("interval", "1h"));
+        let server = serve(mock, "binance/spot_klines.json").await;
+
+        let candles = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        // Derived from the close time, long past for the fixture
+        assert!(candles.iter().all(|c| c.is_closed));
+
+        let flow: Vec<_> = candles
+            .iter()
+            .map(|c| (c.trades, c.taker_buy_volume.map(float), c.taker_buy_quote_volume.map(float)))
+            .collect();
+        assert_eq!(
+            flow,
+            [
+                (Some(10234), Some(61.728), Some(4209216.06172839)),
+                (Some(8123), Some(49.35), Some(3369601.755)),
+                (Some(4012), Some(22.625), Some(1540731.525)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/fapi/v1/klines"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "binance/futures_klines.json").await;
+
+        let candles = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let mock = Mock::given(path("/dapi/v1/klines"))
+            .and(query_param("symbol", "BTCUSD_PERP"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "binance/coin_futures_klines.json").await;
+
+        let candles = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSD_PERP", MarketType::Inverse).into())
+            .await
+            .unwrap();
+
+        // Volumes are read in the base asset rather than in contracts
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert!(candles.iter().all(|c| c.quote_volume.is_none() && c.taker_buy_quote_volume.is_none()));
+        assert_eq!(
+            candles.iter().map(|c| c.taker_buy_volume.map(float)).collect::<Vec<_>>(),
+            [Some(61.728), Some(49.35), Some(22.625)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_routes_by_margin() {
+        let server = serve(
+            Mock::given(path("/fapi/v1/klines")).and(query_param("symbol", "BTCUSDT_250328")),
+            "binance/futures_klines.json",
+        )
+        .await;
+        Mock::given(path("/dapi/v1/klines"))
+            .and(query_param("symbol", "BTCUSD_250328"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(fixture("binance/coin_futures_klines.json"), "application/json"))
+            .mount(&server)
+            .await;
+        let binance = Binance::new(config(&server));
+
+        let candles = binance
+            .get_candles(instrument(Connection::Binance, "BTCUSDT_250328", MarketType::Delivery).into())
+            .await
+            .unwrap();
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+
+        let candles = binance
+            .get_candles(instrument(Connection::Binance, "BTCUSD_250328", MarketType::Delivery).into())
+            .await
+            .unwrap();
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert!(candles.iter().all(|c| c.quote_volume.is_none()));
+
+        // A perpetual symbol has no expiry to tell the margin from
+        let result = binance.get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Delivery).into()).await;
+        assert!(matches!(result, Err(CandlesError::SymbolNotFound { connection: Connection::Binance, symbol }) if symbol == "BTCUSDT"));
+    }
+
+    #[tokio::test]
+    async fn test_option_candles() {
+        let mock = Mock::given(path("/eapi/v1/klines"))
+            .and(query_param("symbol", "BTC-250328-100000-C"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "binance/option_klines.json").await;
+
+        let candles = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTC-250328-100000-C", MarketType::Options).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        assert_eq!(candles.iter().map(|c| c.trades).collect::<Vec<_>>(), [Some(10234), Some(8123), Some(4012)]);
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/fapi/v1/markPriceKlines")).and(query_param("symbol", "BTCUSDT"));
+        let server = serve(mock, "binance/mark_price_klines.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::Binance, "BTCUSDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = Binance::new(config(&server)).get_candles(request).await.unwrap();
+
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert!(candles.iter().all(|c| c.quote_volume.is_none() && c.trades.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_index_price_candles_by_pair() {
+        let mock = Mock::given(path("/dapi/v1/indexPriceKlines"))
+            .and(query_param("pair", "BTCUSD"))
+            .and(query_param_is_missing("symbol"));
+        let server = serve(mock, "binance/mark_price_klines.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::Binance, "BTCUSD_PERP", MarketType::Inverse)).with_price_source(PriceSource::Index);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_PRICE_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_spot_mark_price_is_unsupported() {
+        let request = CandlesRequest::from(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot)).with_price_source(PriceSource::Mark);
+        let result = Binance::default().get_candles(request).await;
+
+        assert!(matches!(
+            result,
+            Err(CandlesError::UnsupportedPriceSource {
+                price_source: PriceSource::Mark,
+                market_type: MarketType::Spot,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_inverse_wide_range_is_cut_locally() {
+        let mock = Mock::given(path("/dapi/v1/klines")).and(query_param_is_missing("endTime"));
+        let server = serve(mock, "binance/coin_futures_klines.json").await;
+
+        // COIN-M klines reject ranges over 200 days, so only the start is sent
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSD_PERP", MarketType::Inverse))
+            .with_start(1710003600000 - 300 * 24 * 3_600_000)
+            .with_end(1710003600000);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES[..2]);
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/api/v3/klines"))
+            .and(query_param("startTime", "1710000000000"))
+            .and(query_param("endTime", "1710007200000"));
+        let server = serve(mock, "binance/spot_klines.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(429, r#"{"code":-1003,"msg":"Too many requests"}"#).await;
+
+        let result = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::RateLimited { retry_after: None })));
+    }
+
+    #[tokio::test]
+    async fn test_ip_ban() {
+        let server = serve_status(418, r#"{"code":-1003,"msg":"Way too many requests; IP banned"}"#).await;
+
+        let result = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::IpBanned { retry_after: None })));
+    }
+
+    #[tokio::test]
+    async fn test_symbol_not_found() {
+        let server = serve_status(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#).await;
+
+        let result = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "NOPEUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::SymbolNotFound { symbol, .. }) if symbol == "NOPEUSDT"));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_candle() {
+        let server = serve_status(200, r#"[[1710000000000, "68000.1", "not a price", "67800.3", "68200.4", "123.456"]]"#).await;
+
+        let result = Binance::new(config(&server))
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::Parse { field, index: 0, .. }) if field == "high price"));
+    }
+
+    #[tokio::test]
+    async fn test_limit_is_capped_to_page_size() {
+        let mock = Mock::given(path("/fapi/v1/klines")).and(query_param("limit", "1500"));
+        let server = serve(mock, "binance/futures_klines.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSDT", MarketType::Derivatives)).with_limit(1500);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_limit_beyond_page_size_paginates() {
+        let mock = Mock::given(path("/api/v3/klines")).and(query_param("limit", "1000")).and(query_param_is_missing("endTime"));
+        let server = serve(mock, "binance/spot_klines.json").await;
+
+        // The second page ends right before the first one and is empty, so the history is exhausted
+        Mock::given(path("/api/v3/klines"))
+            .and(query_param("endTime", "1709999999999"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot)).with_limit(2500);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_limit_keeps_latest_candles() {
+        let server = serve(Mock::given(path("/api/v3/klines")).and(query_param("limit", "2")), "binance/spot_klines.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot)).with_limit(2);
+
+        // The stand-in ignores the limit, the client still trims to the latest two
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_limited_range_keeps_latest_candles() {
+        // Binance fills a ranged page from its start, so only the end is sent and the start applied locally
+        let mock = Mock::given(path("/api/v3/klines"))
+            .and(query_param_is_missing("startTime"))
+            .and(query_param("endTime", "1710007200000"))
+            .and(query_param("limit", "2"));
+        let server = serve(mock, "binance/spot_klines.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot))
+            .with_start(1710000000000)
+            .with_end(1710007200000)
+            .with_limit(2);
+
+        assert_candles(&Binance::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_extended_timeframes() {
+        let server = serve(Mock::given(path("/fapi/v1/klines")).and(query_param("interval", "12h")), "binance/futures_klines.json").await;
+
+        let mut instrument = instrument(Connection::Binance, "BTCUSDT", MarketType::Derivatives);
+        instrument.timeframe = Timeframe::H12;
+        assert_eq!(Binance::new(config(&server)).get_candles(instrument.clone().into()).await.unwrap().len(), 3);
+
+        // Second klines are only served for spot
+        instrument.timeframe = Timeframe::S1;
+        let result = Binance::default().get_candles(instrument.into()).await;
+        assert!(matches!(result, Err(CandlesError::UnsupportedTimeframe { timeframe: Timeframe::S1, .. })));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
+
+    use crate::{
+        base::ExchangeClient,
         binance::main::Binance,
         connections::Connection,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +331,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Binance::get_candles(instrument).await {
+        match Binance::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +347,23 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Binance::get_candles(instrument).await {
+        match Binance::default().get_candles(instrument.into()).await {
+            Ok(result) => examine_candles(&result),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let instrument = Instrument {
+            asset_id: "bitcoin".to_owned(),
+            pair: "BTCUSD_PERP".to_owned(),
+            connection: Connection::Binance,
+            market_type: MarketType::Inverse,
+            timeframe: Timeframe::H1,
+        };
+
+        match Binance::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -1,73 +1,293 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
+    delivery::DeliveryContract,
     errors::CandlesError,
-    types::{Candle, Instrument, MarketType, Timeframe},
-    utils::parse_string_to_f64,
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{candle_array, map_msg_code_error, mark_closed, parse_count, parse_number, parse_optional_number, parse_timestamp, price_candle},
 };
 
-pub struct Binance;
+pub const SPOT_BASE_URL: &str = "https://www.binance.com";
+pub const DERIVATIVES_BASE_URL: &str = "https://fapi.binance.com";
+pub const INVERSE_BASE_URL: &str = "https://dapi.binance.com";
+pub const OPTIONS_BASE_URL: &str = "https://eapi.binance.com";
+
+pub const SPOT_TESTNET_BASE_URL: &str = "https://testnet.binance.vision";
+pub const DERIVATIVES_TESTNET_BASE_URL: &str = "https://testnet.binancefuture.com";
+pub const US_SPOT_BASE_URL: &str = "https://api.binance.us";
+pub const VISION_SPOT_BASE_URL: &str = "https://api.binance.vision";
+
+const INVALID_SYMBOL_CODE: i64 = -1121;
+
+// Documented per-IP budgets: 6000 request weight per minute on spot, 2400 on USDⓈ-M and COIN-M futures
+static SPOT_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(6000, Duration::from_secs(60))).with_used_weight_header(USED_WEIGHT_HEADER));
+static DERIVATIVES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(2400, Duration::from_secs(60))).with_used_weight_header(USED_WEIGHT_HEADER));
+static INVERSE_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(2400, Duration::from_secs(60))).with_used_weight_header(USED_WEIGHT_HEADER));
+// Options allow 400 request weight per minute
+static OPTIONS_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(400, Duration::from_secs(60))).with_used_weight_header(USED_WEIGHT_HEADER));
+
+const USED_WEIGHT_HEADER: &str = "x-mbx-used-weight-1m";
+const SPOT_KLINES_WEIGHT: u32 = 2;
+const OPTIONS_KLINES_WEIGHT: u32 = 1;
+const DEFAULT_LIMIT: usize = 500;
+const SPOT_MAX_LIMIT: usize = 1000;
+const DERIVATIVES_MAX_LIMIT: usize = 1500;
+/// COIN-M klines reject ranges wider than this
+const INVERSE_MAX_RANGE: i64 = 200 * 24 * 60 * 60 * 1000;
+
+/// Futures klines cost more the more candles are asked for
+fn futures_klines_weight(limit: usize) -> u32 {
+    match limit {
+        0..100 => 1,
+        100..500 => 2,
+        500..=1000 => 5,
+        _ => 10,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Binance {
+    config: ClientConfig,
+}
+
+impl Binance {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for Binance {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, CandlesError> {
-        let binance_timeframe = match instrument.timeframe {
-            Timeframe::M3 => "3m",
-            Timeframe::M5 => "5m",
-            Timeframe::M15 => "15m",
-            Timeframe::M30 => "30m",
-            Timeframe::H1 => "1h",
-            Timeframe::H4 => "4h",
-            Timeframe::D1 => "1d",
-            Timeframe::W1 => "1w",
-            Timeframe::MN1 => "1M",
+impl ExchangeClient for Binance {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        let binance_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::Binance,
+            timeframe: instrument.timeframe.clone(),
+        })?;
+
+        // COIN-M serves inverse perpetuals and coin-margined delivery contracts, USDⓈ-M every other future
+        let coin_margined = match instrument.market_type {
+            MarketType::Inverse => true,
+            MarketType::Delivery => DeliveryContract::of(instrument)?.is_inverse(),
+            MarketType::Spot | MarketType::Derivatives | MarketType::Options => false,
         };
 
-        let url = match instrument.market_type {
-            MarketType::Spot => format!("https://www.binance.com/api/v3/klines?symbol={}&interval={}", instrument.pair, binance_timeframe),
-            MarketType::Derivatives => format!("https://fapi.binance.com/fapi/v1/klines?symbol={}&interval={}", instrument.pair, binance_timeframe),
+        // Futures also publish candles of their mark price, index price and premium index
+        let klines = match (&instrument.market_type, request.price_source) {
+            (_, PriceSource::Last) => "klines",
+            (MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery, PriceSource::Mark) => "markPriceKlines",
+            (MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery, PriceSource::Index) => "indexPriceKlines",
+            (MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery, PriceSource::Premium) => "premiumIndexKlines",
+            _ => return Err(CandlesError::unsupported_price_source(Connection::Binance, &request)),
         };
 
-        let response = reqwest::get(&url).await.map_err(|e| CandlesError::ApiError(e.to_string()))?;
+        // The index is kept per underlying pair, shared by its perpetual and delivery contracts: BTCUSD_PERP is priced off BTCUSD
+        let symbol = match request.price_source {
+            PriceSource::Index => format!("pair={}", instrument.pair.split('_').next().unwrap_or_default()),
+            _ => format!("symbol={}", instrument.pair),
+        };
+
+        let mut url = match (&instrument.market_type, coin_margined) {
+            (MarketType::Spot, _) => format!(
+                "{}/api/v3/klines?symbol={}&interval={}",
+                self.config.base_url(&instrument.market_type, SPOT_BASE_URL),
+                instrument.pair,
+                binance_timeframe
+            ),
+            (MarketType::Options, _) => format!(
+                "{}/eapi/v1/klines?symbol={}&interval={}",
+                self.config.base_url(&instrument.market_type, OPTIONS_BASE_URL),
+                instrument.pair,
+                binance_timeframe
+            ),
+            (_, false) => format!(
+                "{}/fapi/v1/{klines}?{symbol}&interval={}",
+                self.config.base_url(&instrument.market_type, DERIVATIVES_BASE_URL),
+                binance_timeframe
+            ),
+            (_, true) => format!(
+                "{}/dapi/v1/{klines}?{symbol}&interval={}",
+                self.config.base_url(&instrument.market_type, INVERSE_BASE_URL),
+                binance_timeframe
+            ),
+        };
 
-        if !response.status().is_success() {
-            return Err(CandlesError::Other(format!(
-                "API request failed with status {}: {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            )));
+        // Pages fill from the start, so a COIN-M range that is too wide is left open ended and cut locally
+        let end = match (request.start, request.end) {
+            (Some(start), Some(end)) if coin_margined && end - start > INVERSE_MAX_RANGE => None,
+            (_, end) => end,
+        };
+
+        if let Some(start) = request.start {
+            url.push_str(&format!("&startTime={start}"));
+        }
+        if let Some(end) = end {
+            url.push_str(&format!("&endTime={end}"));
         }
+        if let Some(limit) = request.limit {
+            url.push_str(&format!("&limit={}", limit.min(self.max_page_size(&request))));
+        }
+
+        let (limiter, weight) = match instrument.market_type {
+            MarketType::Spot => (&*SPOT_LIMITER, SPOT_KLINES_WEIGHT),
+            MarketType::Options => (&*OPTIONS_LIMITER, OPTIONS_KLINES_WEIGHT),
+            MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery => {
+                let limit = request.limit.map_or(DEFAULT_LIMIT, |limit| limit.min(DERIVATIVES_MAX_LIMIT));
+                let limiter = match coin_margined {
+                    true => &*INVERSE_LIMITER,
+                    false => &*DERIVATIVES_LIMITER,
+                };
+                (limiter, futures_klines_weight(limit))
+            }
+        };
 
-        let candles_api = response
-            .json::<Vec<Value>>()
+        let candles_api: Vec<Value> = self
+            .config
+            .get_json(&url, limiter, weight)
             .await
-            .map_err(|e| CandlesError::Other(format!("Failed to parse JSON response: {e}")))?;
+            .map_err(|e| map_msg_code_error(e, Connection::Binance, &instrument.pair, INVALID_SYMBOL_CODE))?;
 
         let mut candles = Vec::with_capacity(candles_api.len());
 
         for (index, value) in candles_api.iter().enumerate() {
-            let candle_array = value.as_array().ok_or(CandlesError::Other(format!("Expected array for candle data at index {index}")))?;
-
-            if candle_array.len() < 6 {
-                return Err(CandlesError::Other(format!(
-                    "Insufficient data in candle array at index {}: expected at least 6 elements, got {}",
-                    index,
-                    candle_array.len()
-                )));
+            if instrument.market_type == MarketType::Options {
+                candles.push(option_candle(value, index)?);
+                continue;
+            }
+            if request.price_source != PriceSource::Last {
+                candles.push(price_candle(value, index)?);
+                continue;
+            }
+
+            let candle_array = candle_array(value, index, 6)?;
+
+            let mut candle = Candle {
+                timestamp: parse_timestamp(&candle_array[0], index)?,
+                open: parse_number(&candle_array[1], "open price", index)?,
+                high: parse_number(&candle_array[2], "high price", index)?,
+                low: parse_number(&candle_array[3], "low price", index)?,
+                close: parse_number(&candle_array[4], "close price", index)?,
+                volume: parse_number(&candle_array[5], "volume", index)?,
+                quote_volume: parse_optional_number(candle_array.get(7), "quote volume", index)?,
+                trades: candle_array.get(8).map(|val| parse_count(val, "trades", index)).transpose()?,
+                taker_buy_volume: parse_optional_number(candle_array.get(9), "taker buy volume", index)?,
+                taker_buy_quote_volume: parse_optional_number(candle_array.get(10), "taker buy quote volume", index)?,
+                ..Default::default()
+            };
+
+            // COIN-M volumes are counted in contracts, with the base asset volumes where quote volumes usually are
+            if coin_margined {
+                candle.volume = candle.quote_volume.take().ok_or_else(|| CandlesError::parse("volume", index, value))?;
+                candle.taker_buy_volume = candle.taker_buy_quote_volume.take();
             }
 
-            candles.push(Candle {
-                timestamp: candle_array[0].as_i64().ok_or(CandlesError::Other(format!("Invalid timestamp at index {index}")))?,
-                open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                volume: parse_string_to_f64(&candle_array[5], "volume", index)?,
-            });
+            candles.push(candle);
+        }
+
+        if end != request.end {
+            candles.retain(|c| request.contains(c.timestamp));
         }
 
+        mark_closed(&mut candles, &instrument.timeframe);
+
         Ok(candles)
     }
+
+    fn max_page_size(&self, request: &CandlesRequest) -> usize {
+        match request.instrument.market_type {
+            MarketType::Spot => SPOT_MAX_LIMIT,
+            MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery | MarketType::Options => DERIVATIVES_MAX_LIMIT,
+        }
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Forward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: match market_type {
+                MarketType::Spot => SPOT_MAX_LIMIT,
+                MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery | MarketType::Options => DERIVATIVES_MAX_LIMIT,
+            },
+            max_history: None,
+            price_source_max_history: None,
+            // Delivery symbols may be either margin type, so only the fields both have are listed
+            extended_fields: match market_type {
+                MarketType::Inverse | MarketType::Delivery => vec![ExtendedField::Trades, ExtendedField::TakerBuyVolume],
+                _ => vec![
+                    ExtendedField::QuoteVolume,
+                    ExtendedField::Trades,
+                    ExtendedField::TakerBuyVolume,
+                    ExtendedField::TakerBuyQuoteVolume,
+                ],
+            },
+            price_sources: match market_type {
+                MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery => vec![PriceSource::Last, PriceSource::Mark, PriceSource::Index, PriceSource::Premium],
+                MarketType::Spot | MarketType::Options => vec![PriceSource::Last],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: false,
+        })
+    }
+}
+
+/// Option klines are objects rather than arrays, with volumes in contracts of one base unit
+fn option_candle(value: &Value, index: usize) -> Result<Candle, CandlesError> {
+    let field = |key: &str| value.get(key).ok_or_else(|| CandlesError::parse(key, index, value));
+
+    Ok(Candle {
+        timestamp: parse_timestamp(field("openTime")?, index)?,
+        open: parse_number(field("open")?, "open price", index)?,
+        high: parse_number(field("high")?, "high price", index)?,
+        low: parse_number(field("low")?, "low price", index)?,
+        close: parse_number(field("close")?, "close price", index)?,
+        volume: parse_number(field("volume")?, "volume", index)?,
+        quote_volume: parse_optional_number(value.get("amount"), "quote volume", index)?,
+        trades: value.get("tradeCount").map(|val| parse_count(val, "trades", index)).transpose()?,
+        taker_buy_volume: parse_optional_number(value.get("takerVolume"), "taker buy volume", index)?,
+        taker_buy_quote_volume: parse_optional_number(value.get("takerAmount"), "taker buy quote volume", index)?,
+        ..Default::default()
+    })
+}
+
+/// The Binance interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        // Second klines are spot only
+        Timeframe::S1 if *market_type == MarketType::Spot => "1s",
+        Timeframe::M1 => "1m",
+        Timeframe::M3 => "3m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::M30 => "30m",
+        Timeframe::H1 => "1h",
+        Timeframe::H2 => "2h",
+        Timeframe::H4 => "4h",
+        Timeframe::H6 => "6h",
+        // Option klines stop at weeks and skip 8 hours
+        Timeframe::H8 | Timeframe::MN1 if *market_type == MarketType::Options => return None,
+        Timeframe::H8 => "8h",
+        Timeframe::H12 => "12h",
+        Timeframe::D1 => "1d",
+        Timeframe::D3 => "3d",
+        Timeframe::W1 => "1w",
+        Timeframe::MN1 => "1M",
+        Timeframe::S1 => return None,
+    };
+
+    Some(interval)
 }
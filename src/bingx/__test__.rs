@@ -1,8 +1,109 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock,
+        matchers::{path, query_param},
+    };
+
+    use crate::{
+        base::ExchangeClient,
+        bingx::main::BingX,
+        connections::Connection,
+        errors::CandlesError,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/openApi/spot/v2/market/kline"))
+            .and(query_param("symbol", "BTC-USDT"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "bingx/spot_kline.json").await;
+
+        let candles = BingX::new(config(&server))
+            .get_candles(instrument(Connection::BingX, "BTC-USDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/openApi/swap/v3/quote/klines"))
+            .and(query_param("symbol", "BTC-USDT"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "bingx/swap_klines.json").await;
+
+        let candles = BingX::new(config(&server))
+            .get_candles(instrument(Connection::BingX, "BTC-USDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        // Swap klines carry no turnover
+        assert!(candles.iter().all(|c| c.quote_volume.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/openApi/swap/v3/quote/klines"))
+            .and(query_param("startTime", "1710000000000"))
+            .and(query_param("endTime", "1710007200000"));
+        let server = serve(mock, "bingx/swap_klines.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::BingX, "BTC-USDT", MarketType::Derivatives))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&BingX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/openApi/swap/v1/market/markPriceKlines"))
+            .and(query_param("symbol", "BTC-USDT"))
+            .and(query_param("interval", "1h"));
+        let server = serve(mock, "bingx/mark_price_klines.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::BingX, "BTC-USDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = BingX::new(config(&server)).get_candles(request).await.unwrap();
+
+        // Rows arrive oldest first, unlike the klines, and are keyed by `openTime`
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert!(candles.iter().all(|c| c.is_closed && c.quote_volume.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = BingX::new(config(&server))
+            .get_candles(instrument(Connection::BingX, "BTC-USDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_error() {
+        let server = serve_status(200, r#"{"code":100204,"msg":"symbol not exist","data":{}}"#).await;
+
+        let result = BingX::new(config(&server))
+            .get_candles(instrument(Connection::BingX, "NOPE-USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::ExchangeError { code, message }) if code == "100204" && message == "symbol not exist"));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
         bingx::main::BingX,
         connections::Connection,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +120,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match BingX::get_candles(instrument).await {
+        match BingX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +136,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match BingX::get_candles(instrument).await {
+        match BingX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
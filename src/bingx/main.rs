@@ -1,114 +1,197 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
     errors::CandlesError,
-    types::{Candle, Instrument, MarketType, Timeframe},
-    utils::{DataWrapper, parse_string_to_f64},
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{DataWrapperWithMsgCode, candle_array, mark_closed, parse_number, parse_optional_number, parse_timestamp},
 };
 
-pub struct BingX;
+pub const BASE_URL: &str = "https://open-api.bingx.com";
+
+const SPOT_MAX_LIMIT: usize = 1000;
+const DERIVATIVES_MAX_LIMIT: usize = 1440;
+
+// Documented per-IP limit for market data: 100 requests per 10 seconds
+static LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(100, Duration::from_secs(10))));
+
+#[derive(Debug, Clone, Default)]
+pub struct BingX {
+    config: ClientConfig,
+}
+
+impl BingX {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for BingX {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, CandlesError> {
-        let bingx_timeframe = match instrument.timeframe {
-            Timeframe::M3 => "3m",
-            Timeframe::M5 => "5m",
-            Timeframe::M15 => "15m",
-            Timeframe::M30 => "30m",
-            Timeframe::H1 => "1h",
-            Timeframe::H4 => "4h",
-            Timeframe::D1 => "1d",
-            Timeframe::W1 => "1w",
-            Timeframe::MN1 => "1M",
-        };
+impl ExchangeClient for BingX {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        if !matches!(instrument.market_type, MarketType::Spot | MarketType::Derivatives) {
+            return Err(CandlesError::UnsupportedMarketType {
+                connection: Connection::BingX,
+                market_type: instrument.market_type.clone(),
+            });
+        }
+
+        let bingx_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::BingX,
+            timeframe: instrument.timeframe.clone(),
+        })?;
 
-        let path = match instrument.market_type {
-            MarketType::Spot => "/openApi/spot/v2/market/kline",
-            MarketType::Derivatives => "/openApi/swap/v3/quote/klines",
+        let path = match (request.price_source, &instrument.market_type) {
+            (PriceSource::Last, MarketType::Spot) => "/openApi/spot/v2/market/kline",
+            (PriceSource::Last, _) => "/openApi/swap/v3/quote/klines",
+            (PriceSource::Mark, MarketType::Derivatives) => "/openApi/swap/v1/market/markPriceKlines",
+            _ => return Err(CandlesError::unsupported_price_source(Connection::BingX, &request)),
         };
 
-        let url = format!("https://open-api.bingx.com{path}?symbol={}&interval={}", instrument.pair, bingx_timeframe);
+        let mut url = format!(
+            "{}{path}?symbol={}&interval={}",
+            self.config.base_url(&instrument.market_type, BASE_URL),
+            instrument.pair,
+            bingx_timeframe
+        );
+
+        if let Some(start) = request.start {
+            url.push_str(&format!("&startTime={start}"));
+        }
+        if let Some(end) = request.end {
+            url.push_str(&format!("&endTime={end}"));
+        }
+        if let Some(limit) = request.limit {
+            url.push_str(&format!("&limit={}", limit.min(self.max_page_size(&request))));
+        }
 
-        let response: DataWrapper<Vec<Value>> = reqwest::get(&url).await?.json().await?;
+        // Failed requests may carry `"data": {}`, so the payload is only read as an array once the code is checked
+        let response: DataWrapperWithMsgCode<i64, Value> = self.config.get_envelope(&url, &LIMITER, 1).await?;
+        let data = response.into_data("0")?;
+        let data = data.as_array().ok_or_else(|| CandlesError::parse("data", 0, &data))?;
 
-        let mut candles = Vec::with_capacity(response.data.len());
+        let mut candles = Vec::with_capacity(data.len());
 
-        for (index, value) in response.data.iter().enumerate().rev() {
+        for (index, value) in data.iter().enumerate().rev() {
             match instrument.market_type {
                 MarketType::Spot => {
-                    let candle_array = value
-                        .as_array()
-                        .ok_or_else(|| CandlesError::Other(format!("Expected array for candle data at index {index}")))?;
-
-                    if candle_array.len() < 6 {
-                        return Err(CandlesError::Other(format!(
-                            "Insufficient data in candle array at index {index}: expected at least 6 elements, got {}",
-                            candle_array.len()
-                        )));
-                    }
+                    let candle_array = candle_array(value, index, 6)?;
 
                     candles.push(Candle {
-                        timestamp: candle_array[0]
-                            .as_i64()
-                            .ok_or(CandlesError::Other(format!("Failed to parse timestamp at index {} with value {}", index, candle_array[0])))?,
-                        open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                        high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                        low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                        close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                        volume: parse_string_to_f64(&candle_array[5], "volume", index)?,
+                        timestamp: parse_timestamp(&candle_array[0], index)?,
+                        open: parse_number(&candle_array[1], "open price", index)?,
+                        high: parse_number(&candle_array[2], "high price", index)?,
+                        low: parse_number(&candle_array[3], "low price", index)?,
+                        close: parse_number(&candle_array[4], "close price", index)?,
+                        volume: parse_number(&candle_array[5], "volume", index)?,
+                        quote_volume: parse_optional_number(candle_array.get(7), "quote volume", index)?,
+                        ..Default::default()
                     });
                 }
-                MarketType::Derivatives => {
-                    let candle_object = value
-                        .as_object()
-                        .ok_or_else(|| CandlesError::Other(format!("Expected object for candle data at index {index}")))?;
-
-                    if candle_object.keys().len() < 6 {
-                        return Err(CandlesError::Other(format!(
-                            "Insufficient data in candle object at index {index}: expected at least 6 elements, got {}",
-                            candle_object.len()
-                        )));
-                    }
+                _ => {
+                    let field = |key: &str| value.get(key).ok_or_else(|| CandlesError::parse(key, index, value));
+                    // Mark price klines carry `openTime` in place of `time`
+                    let timestamp = value
+                        .get("time")
+                        .or_else(|| value.get("openTime"))
+                        .ok_or_else(|| CandlesError::parse("time", index, value))?;
 
                     candles.push(Candle {
-                        timestamp: candle_object
-                            .get("time")
-                            .ok_or(CandlesError::Other(format!("Failed to get timestamp for key 'time' at index {index}")))?
-                            .as_i64()
-                            .ok_or(CandlesError::Other(format!("Failed to parse timestamp at index {index}")))?,
-                        open: parse_string_to_f64(
-                            candle_object.get("open").ok_or(CandlesError::Other(format!("Failed to get 'open' at index {index}")))?,
-                            "open price",
-                            index,
-                        )?,
-                        high: parse_string_to_f64(
-                            candle_object.get("high").ok_or(CandlesError::Other(format!("Failed to get 'high' at index {index}")))?,
-                            "high price",
-                            index,
-                        )?,
-                        low: parse_string_to_f64(
-                            candle_object.get("low").ok_or(CandlesError::Other(format!("Failed to get 'low' at index {index}")))?,
-                            "low price",
-                            index,
-                        )?,
-                        close: parse_string_to_f64(
-                            candle_object.get("close").ok_or(CandlesError::Other(format!("Failed to get 'close' at index {index}")))?,
-                            "close price",
-                            index,
-                        )?,
-                        volume: parse_string_to_f64(
-                            candle_object.get("volume").ok_or(CandlesError::Other(format!("Failed to get 'volume' at index {index}")))?,
-                            "volume",
-                            index,
-                        )?,
+                        timestamp: parse_timestamp(timestamp, index)?,
+                        open: parse_number(field("open")?, "open price", index)?,
+                        high: parse_number(field("high")?, "high price", index)?,
+                        low: parse_number(field("low")?, "low price", index)?,
+                        close: parse_number(field("close")?, "close price", index)?,
+                        volume: match request.price_source {
+                            PriceSource::Last => parse_number(field("volume")?, "volume", index)?,
+                            _ => Default::default(),
+                        },
+                        ..Default::default()
                     });
                 }
             }
         }
 
+        // Mark price klines are not documented to share the newest-first order of the klines
+        if request.price_source != PriceSource::Last {
+            candles.sort_by_key(|c| c.timestamp);
+        }
+
+        mark_closed(&mut candles, &instrument.timeframe);
+
         Ok(candles)
     }
+
+    fn max_page_size(&self, request: &CandlesRequest) -> usize {
+        match request.instrument.market_type {
+            MarketType::Spot => SPOT_MAX_LIMIT,
+            _ => DERIVATIVES_MAX_LIMIT,
+        }
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Backward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        if !matches!(market_type, MarketType::Spot | MarketType::Derivatives) {
+            return None;
+        }
+
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: match market_type {
+                MarketType::Spot => SPOT_MAX_LIMIT,
+                _ => DERIVATIVES_MAX_LIMIT,
+            },
+            max_history: None,
+            price_source_max_history: None,
+            extended_fields: match market_type {
+                MarketType::Spot => vec![ExtendedField::QuoteVolume],
+                _ => vec![],
+            },
+            price_sources: match market_type {
+                MarketType::Spot => vec![PriceSource::Last],
+                _ => vec![PriceSource::Last, PriceSource::Mark],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: false,
+        })
+    }
+}
+
+/// The BingX interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, _market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        Timeframe::M1 => "1m",
+        Timeframe::M3 => "3m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::M30 => "30m",
+        Timeframe::H1 => "1h",
+        Timeframe::H2 => "2h",
+        Timeframe::H4 => "4h",
+        Timeframe::H6 => "6h",
+        Timeframe::H8 => "8h",
+        Timeframe::H12 => "12h",
+        Timeframe::D1 => "1d",
+        Timeframe::D3 => "3d",
+        Timeframe::W1 => "1w",
+        Timeframe::MN1 => "1M",
+        Timeframe::S1 => return None,
+    };
+
+    Some(interval)
 }
@@ -1,8 +1,92 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock,
+        matchers::{path, query_param},
+    };
+
+    use crate::{
+        base::ExchangeClient,
+        blofin::main::BloFin,
+        connections::Connection,
+        errors::CandlesError,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource},
+    };
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/api/v1/market/candles"))
+            .and(query_param("instId", "BTC-USDT"))
+            .and(query_param("bar", "1H"));
+        let server = serve(mock, "blofin/candles.json").await;
+
+        let candles = BloFin::new(config(&server))
+            .get_candles(instrument(Connection::BloFin, "BTC-USDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        assert_eq!(candles.iter().map(|c| c.is_closed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/api/v1/market/candles"))
+            .and(query_param("after", "1710007200001"))
+            .and(query_param("before", "1709999999999"));
+        let server = serve(mock, "blofin/candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::BloFin, "BTC-USDT", MarketType::Derivatives))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&BloFin::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/api/v1/market/mark-price-candles"))
+            .and(query_param("instId", "BTC-USDT"))
+            .and(query_param("bar", "1H"));
+        let server = serve(mock, "blofin/mark_price_candles.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::BloFin, "BTC-USDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = BloFin::new(config(&server)).get_candles(request).await.unwrap();
+
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert_eq!(candles.iter().map(|c| c.is_closed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = BloFin::new(config(&server))
+            .get_candles(instrument(Connection::BloFin, "BTC-USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_error() {
+        let server = serve_status(200, r#"{"code":"152001","msg":"Parameter bar error"}"#).await;
+
+        let result = BloFin::new(config(&server))
+            .get_candles(instrument(Connection::BloFin, "BTC-USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::ExchangeError { code, .. }) if code == "152001"));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
         blofin::main::BloFin,
         connections::Connection,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +103,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match BloFin::get_candles(instrument).await {
+        match BloFin::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +119,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match BloFin::get_candles(instrument).await {
+        match BloFin::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -1,63 +1,172 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
     errors::CandlesError,
-    types::{Candle, Instrument, Timeframe},
-    utils::{DataWrapper, parse_string_to_f64},
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{DataWrapperWithMsgCode, candle_array, parse_number, parse_optional_number, parse_timestamp, price_candle},
 };
 
-pub struct BloFin;
+pub const BASE_URL: &str = "https://openapi.blofin.com";
+
+const MAX_LIMIT: usize = 1440;
+
+// Documented per-IP limit for public endpoints: 500 requests per minute
+static LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(500, Duration::from_secs(60))));
+
+#[derive(Debug, Clone, Default)]
+pub struct BloFin {
+    config: ClientConfig,
+}
+
+impl BloFin {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for BloFin {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, CandlesError> {
-        let blofin_timeframe = match instrument.timeframe {
-            Timeframe::M3 => "3m",
-            Timeframe::M5 => "5m",
-            Timeframe::M15 => "15m",
-            Timeframe::M30 => "30m",
-            Timeframe::H1 => "1H",
-            Timeframe::H4 => "4H",
-            Timeframe::D1 => "1D",
-            Timeframe::W1 => "1W",
-            Timeframe::MN1 => "1M",
+impl ExchangeClient for BloFin {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        if !matches!(instrument.market_type, MarketType::Spot | MarketType::Derivatives) {
+            return Err(CandlesError::UnsupportedMarketType {
+                connection: Connection::BloFin,
+                market_type: instrument.market_type.clone(),
+            });
+        }
+
+        let blofin_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::BloFin,
+            timeframe: instrument.timeframe.clone(),
+        })?;
+
+        let endpoint = match (request.price_source, &instrument.market_type) {
+            (PriceSource::Last, _) => "candles",
+            (PriceSource::Mark, MarketType::Derivatives) => "mark-price-candles",
+            _ => return Err(CandlesError::unsupported_price_source(Connection::BloFin, &request)),
         };
 
-        let url = format!("https://openapi.blofin.com/api/v1/market/candles?instId={}&bar={}", instrument.pair, blofin_timeframe);
+        let mut url = format!(
+            "{}/api/v1/market/{}?instId={}&bar={}",
+            self.config.base_url(&instrument.market_type, BASE_URL),
+            endpoint,
+            instrument.pair,
+            blofin_timeframe
+        );
 
-        let response: DataWrapper<Vec<Value>> = reqwest::get(&url).await?.json().await?;
+        // BloFin bounds are exclusive: `after` returns older records, `before` returns newer ones
+        if let Some(end) = request.end {
+            url.push_str(&format!("&after={}", end + 1));
+        }
+        if let Some(start) = request.start {
+            url.push_str(&format!("&before={}", start - 1));
+        }
+        if let Some(limit) = request.limit {
+            url.push_str(&format!("&limit={}", limit.min(MAX_LIMIT)));
+        }
 
-        let mut candles = Vec::with_capacity(response.data.len());
+        let response: DataWrapperWithMsgCode<String, Vec<Value>> = self.config.get_envelope(&url, &LIMITER, 1).await?;
+        let data = response.into_data("0")?;
 
-        for (index, value) in response.data.iter().enumerate().rev() {
-            let candle_array = value
-                .as_array()
-                .ok_or_else(|| CandlesError::Other(format!("Expected array for candle data at index {index}")))?;
+        let mut candles = Vec::with_capacity(data.len());
 
-            if candle_array.len() < 6 {
-                return Err(CandlesError::Other(format!(
-                    "Insufficient data in candle array at index {}: expected at least 6 elements, got {}",
-                    index,
-                    candle_array.len()
-                )));
+        for (index, value) in data.iter().enumerate().rev() {
+            // Mark price rows are [ts, open, high, low, close, confirm]
+            if request.price_source != PriceSource::Last {
+                let confirm = value.as_array().and_then(|row| row.get(5)).and_then(Value::as_str);
+
+                candles.push(Candle {
+                    is_closed: confirm == Some("1"),
+                    ..price_candle(value, index)?
+                });
+                continue;
             }
 
+            let candle_array = candle_array(value, index, 7)?;
+
             candles.push(Candle {
-                timestamp: candle_array[0]
-                    .as_str()
-                    .ok_or_else(|| CandlesError::Other(format!("Invalid timestamp at index {} with value {}", index, candle_array[0])))?
-                    .parse::<i64>()
-                    .map_err(|_| CandlesError::Other(format!("Failed to parse timestamp at index {} with value {}", index, candle_array[0])))?,
-                open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                volume: parse_string_to_f64(&candle_array[6], "volume", index)?,
+                timestamp: parse_timestamp(&candle_array[0], index)?,
+                open: parse_number(&candle_array[1], "open price", index)?,
+                high: parse_number(&candle_array[2], "high price", index)?,
+                low: parse_number(&candle_array[3], "low price", index)?,
+                close: parse_number(&candle_array[4], "close price", index)?,
+                volume: parse_number(&candle_array[6], "volume", index)?,
+                // volCurrencyQuote
+                quote_volume: parse_optional_number(candle_array.get(7), "quote volume", index)?,
+                is_closed: candle_array.get(8).is_some_and(|confirm| *confirm == "1"),
+                ..Default::default()
             });
         }
 
+        // Mark price candles are not documented to share the newest-first order of the candles
+        if request.price_source != PriceSource::Last {
+            candles.sort_by_key(|c| c.timestamp);
+        }
+
         Ok(candles)
     }
+
+    fn max_page_size(&self, _request: &CandlesRequest) -> usize {
+        MAX_LIMIT
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Backward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        if !matches!(market_type, MarketType::Spot | MarketType::Derivatives) {
+            return None;
+        }
+
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: MAX_LIMIT,
+            max_history: None,
+            price_source_max_history: None,
+            extended_fields: vec![ExtendedField::QuoteVolume],
+            price_sources: match market_type {
+                MarketType::Derivatives => vec![PriceSource::Last, PriceSource::Mark],
+                _ => vec![PriceSource::Last],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: true,
+        })
+    }
+}
+
+/// The BloFin interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, _market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        Timeframe::M1 => "1m",
+        Timeframe::M3 => "3m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::M30 => "30m",
+        Timeframe::H1 => "1H",
+        Timeframe::H2 => "2H",
+        Timeframe::H4 => "4H",
+        Timeframe::H6 => "6H",
+        Timeframe::H8 => "8H",
+        Timeframe::H12 => "12H",
+        Timeframe::D1 => "1D",
+        Timeframe::D3 => "3D",
+        Timeframe::W1 => "1W",
+        Timeframe::MN1 => "1M",
+        Timeframe::S1 => return None,
+    };
+
+    Some(interval)
 }
@@ -1,8 +1,198 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock,
+        matchers::{path, query_param},
+    };
+
+    use crate::{
+        base::ExchangeClient,
+        bybit::main::Bybit,
+        connections::Connection,
+        errors::CandlesError,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/v5/market/kline"))
+            .and(query_param("category", "spot"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("interval", "60"));
+        let server = serve(mock, "bybit/kline.json").await;
+
+        let candles = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "BTCUSDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/v5/market/kline")).and(query_param("category", "linear"));
+        let server = serve(mock, "bybit/kline.json").await;
+
+        let candles = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "BTCUSDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let mock = Mock::given(path("/v5/market/kline"))
+            .and(query_param("category", "inverse"))
+            .and(query_param("symbol", "BTCUSD"));
+        let server = serve(mock, "bybit/inverse_kline.json").await;
+
+        let candles = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "BTCUSD", MarketType::Inverse).into())
+            .await
+            .unwrap();
+
+        // Inverse volume is reported in USD and turnover in the coin
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_inverse_delivery_candles() {
+        let mock = Mock::given(path("/v5/market/kline"))
+            .and(query_param("category", "inverse"))
+            .and(query_param("symbol", "BTCUSDH25"));
+        let server = serve(mock, "bybit/inverse_kline.json").await;
+
+        let candles = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "BTCUSDH25", MarketType::Delivery).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_options_unsupported() {
+        let result = Bybit::default()
+            .get_candles(instrument(Connection::Bybit, "BTC-28MAR25-100000-C", MarketType::Options).into())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(CandlesError::UnsupportedMarketType {
+                market_type: MarketType::Options,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/v5/market/mark-price-kline"))
+            .and(query_param("category", "linear"))
+            .and(query_param("symbol", "BTCUSDT"));
+        let server = serve(mock, "bybit/mark_price_kline.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::Bybit, "BTCUSDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = Bybit::new(config(&server)).get_candles(request).await.unwrap();
+
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert!(candles.iter().all(|c| c.is_closed));
+    }
+
+    #[tokio::test]
+    async fn test_index_price_candles() {
+        let mock = Mock::given(path("/v5/market/index-price-kline"))
+            .and(query_param("category", "inverse"))
+            .and(query_param("symbol", "BTCUSD"));
+        let server = serve(mock, "bybit/mark_price_kline.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::Bybit, "BTCUSD", MarketType::Inverse)).with_price_source(PriceSource::Index);
+
+        assert_candles(&Bybit::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_PRICE_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_premium_index_is_linear_only() {
+        let request = CandlesRequest::from(instrument(Connection::Bybit, "BTCUSD", MarketType::Inverse)).with_price_source(PriceSource::Premium);
+        let result = Bybit::default().get_candles(request).await;
+
+        assert!(matches!(
+            result,
+            Err(CandlesError::UnsupportedPriceSource {
+                price_source: PriceSource::Premium,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/v5/market/kline"))
+            .and(query_param("start", "1710000000000"))
+            .and(query_param("end", "1710007200000"));
+        let server = serve(mock, "bybit/kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Bybit, "BTCUSDT", MarketType::Spot))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&Bybit::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "BTCUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_symbol_not_found() {
+        let server = serve_status(
+            200,
+            r#"{"retCode":10001,"retMsg":"Not supported symbols","result":{},"retExtInfo":{},"time":1710008000000}"#,
+        )
+        .await;
+
+        let result = Bybit::new(config(&server))
+            .get_candles(instrument(Connection::Bybit, "NOPEUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::SymbolNotFound { symbol, .. }) if symbol == "NOPEUSDT"));
+    }
+
+    #[tokio::test]
+    async fn test_extended_timeframes() {
+        let server = serve(Mock::given(path("/v5/market/kline")).and(query_param("interval", "120")), "bybit/kline.json").await;
+
+        let mut instrument = instrument(Connection::Bybit, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::H2;
+        assert_eq!(Bybit::new(config(&server)).get_candles(instrument.clone().into()).await.unwrap().len(), 3);
+
+        // Nothing finer to resample from
+        instrument.timeframe = Timeframe::S1;
+        let result = Bybit::default().get_candles(instrument.into()).await;
+        assert!(matches!(result, Err(CandlesError::UnsupportedTimeframe { timeframe: Timeframe::S1, .. })));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
         bybit::main::Bybit,
         connections::Connection,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +209,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Bybit::get_candles(instrument).await {
+        match Bybit::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +225,23 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Bybit::get_candles(instrument).await {
+        match Bybit::default().get_candles(instrument.into()).await {
+            Ok(result) => examine_candles(&result),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let instrument = Instrument {
+            asset_id: "bitcoin".to_owned(),
+            pair: "BTCUSD".to_owned(),
+            connection: Connection::Bybit,
+            market_type: MarketType::Inverse,
+            timeframe: Timeframe::H1,
+        };
+
+        match Bybit::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
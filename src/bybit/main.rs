@@ -1,70 +1,199 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
     bybit::types::BybitKlineResponse,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
+    delivery::DeliveryContract,
     errors::CandlesError,
-    types::{Candle, MarketType, Timeframe},
-    utils::{ResultWrapper, parse_string_to_f64},
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{ResultWrapperWithRetCode, candle_array, map_symbol_not_found, mark_closed, parse_number, parse_optional_number, parse_timestamp, price_candle},
 };
 
-pub struct Bybit;
+pub const BASE_URL: &str = "https://api.bybit.com";
+pub const TESTNET_BASE_URL: &str = "https://api-testnet.bybit.com";
+
+const INVALID_PARAMETER_CODE: &str = "10001";
+const MAX_LIMIT: usize = 1000;
+
+// Documented per-IP limit for public endpoints: 600 requests per 5 seconds
+static LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(600, Duration::from_secs(5))));
+
+#[derive(Debug, Clone, Default)]
+pub struct Bybit {
+    config: ClientConfig,
+}
+
+impl Bybit {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for Bybit {
-    async fn get_candles(instrument: crate::types::Instrument) -> Result<Vec<crate::types::Candle>, crate::errors::CandlesError> {
-        let bybit_timeframe = match instrument.timeframe {
-            Timeframe::M3 => "3",
-            Timeframe::M5 => "5",
-            Timeframe::M15 => "15",
-            Timeframe::M30 => "30",
-            Timeframe::H1 => "60",
-            Timeframe::H4 => "240",
-            Timeframe::D1 => "D",
-            Timeframe::W1 => "W",
-            Timeframe::MN1 => "M",
-        };
+impl ExchangeClient for Bybit {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        let bybit_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::Bybit,
+            timeframe: instrument.timeframe.clone(),
+        })?;
 
         let category = match instrument.market_type {
             MarketType::Spot => "spot",
             MarketType::Derivatives => "linear",
+            MarketType::Inverse => "inverse",
+            MarketType::Delivery => match DeliveryContract::of(instrument)?.is_inverse() {
+                true => "inverse",
+                false => "linear",
+            },
+            // Option candles are not published through the kline endpoint
+            MarketType::Options => {
+                return Err(CandlesError::UnsupportedMarketType {
+                    connection: Connection::Bybit,
+                    market_type: instrument.market_type.clone(),
+                });
+            }
+        };
+
+        let endpoint = match (request.price_source, category) {
+            (PriceSource::Last, _) => "kline",
+            (PriceSource::Mark, "linear" | "inverse") => "mark-price-kline",
+            (PriceSource::Index, "linear" | "inverse") => "index-price-kline",
+            // Premium index candles are only published for linear contracts
+            (PriceSource::Premium, "linear") => "premium-index-price-kline",
+            _ => return Err(CandlesError::unsupported_price_source(Connection::Bybit, &request)),
         };
 
-        let url = format!(
-            "https://api.bybit.com/v5/market/kline?category={}&symbol={}&interval={}",
-            category, instrument.pair, bybit_timeframe
+        let mut url = format!(
+            "{}/v5/market/{}?category={}&symbol={}&interval={}",
+            self.config.base_url(&instrument.market_type, BASE_URL),
+            endpoint,
+            category,
+            instrument.pair,
+            bybit_timeframe
         );
 
-        let response: ResultWrapper<BybitKlineResponse> = reqwest::get(&url).await?.json().await?;
+        if let Some(start) = request.start {
+            url.push_str(&format!("&start={start}"));
+        }
+        if let Some(end) = request.end {
+            url.push_str(&format!("&end={end}"));
+        }
+        if let Some(limit) = request.limit {
+            url.push_str(&format!("&limit={}", limit.min(MAX_LIMIT)));
+        }
 
-        let mut candles = Vec::with_capacity(response.result.list.len());
+        let response: ResultWrapperWithRetCode<BybitKlineResponse> = self.config.get_envelope(&url, &LIMITER, 1).await?;
+        let result = response.into_result().map_err(|e| {
+            map_symbol_not_found(e, Connection::Bybit, &instrument.pair, |code, message| {
+                code == INVALID_PARAMETER_CODE && message.to_lowercase().contains("symbol")
+            })
+        })?;
 
-        for (index, value) in response.result.list.iter().enumerate().rev() {
-            let candle_array = value
-                .as_array()
-                .ok_or_else(|| CandlesError::Other(format!("Expected array for candle data at index {index}")))?;
+        let mut candles = Vec::with_capacity(result.list.len());
+        // Inverse volume is only in the coin when turnover is present
+        let min_len = match category {
+            "inverse" => 7,
+            _ => 6,
+        };
 
-            if candle_array.len() < 6 {
-                return Err(CandlesError::Other(format!(
-                    "Insufficient data in candle array at index {index}: expected at least 6 elements, got {}",
-                    candle_array.len()
-                )));
+        for (index, value) in result.list.iter().enumerate().rev() {
+            // Price index rows are [startTime, open, high, low, close]
+            if request.price_source != PriceSource::Last {
+                candles.push(price_candle(value, index)?);
+                continue;
             }
 
+            let candle_array = candle_array(value, index, min_len)?;
+
+            // Inverse contracts report volume in USD and turnover in the coin, the other way around
+            let (volume, quote_volume) = match category {
+                "inverse" => (
+                    parse_number(&candle_array[6], "volume", index)?,
+                    Some(parse_number(&candle_array[5], "quote volume", index)?),
+                ),
+                _ => (
+                    parse_number(&candle_array[5], "volume", index)?,
+                    // turnover
+                    parse_optional_number(candle_array.get(6), "quote volume", index)?,
+                ),
+            };
+
             candles.push(Candle {
-                timestamp: candle_array[0]
-                    .as_str()
-                    .ok_or_else(|| CandlesError::Other(format!("Invalid timestamp at index {} with value {}", index, candle_array[0])))?
-                    .parse::<i64>()
-                    .map_err(|_| CandlesError::Other(format!("Failed to parse timestamp at index {} with value {}", index, candle_array[0])))?,
-                open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                volume: parse_string_to_f64(&candle_array[5], "volume", index)?,
+                timestamp: parse_timestamp(&candle_array[0], index)?,
+                open: parse_number(&candle_array[1], "open price", index)?,
+                high: parse_number(&candle_array[2], "high price", index)?,
+                low: parse_number(&candle_array[3], "low price", index)?,
+                close: parse_number(&candle_array[4], "close price", index)?,
+                volume,
+                quote_volume,
+                ..Default::default()
             });
         }
 
+        mark_closed(&mut candles, &instrument.timeframe);
+
         Ok(candles)
     }
+
+    fn max_page_size(&self, _request: &CandlesRequest) -> usize {
+        MAX_LIMIT
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Backward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        if *market_type == MarketType::Options {
+            return None;
+        }
+
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: MAX_LIMIT,
+            max_history: None,
+            price_source_max_history: None,
+            extended_fields: vec![ExtendedField::QuoteVolume],
+            price_sources: match market_type {
+                MarketType::Spot => vec![PriceSource::Last],
+                MarketType::Derivatives => vec![PriceSource::Last, PriceSource::Mark, PriceSource::Index, PriceSource::Premium],
+                _ => vec![PriceSource::Last, PriceSource::Mark, PriceSource::Index],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: false,
+        })
+    }
+}
+
+/// The Bybit interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, _market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        Timeframe::M1 => "1",
+        Timeframe::M3 => "3",
+        Timeframe::M5 => "5",
+        Timeframe::M15 => "15",
+        Timeframe::M30 => "30",
+        Timeframe::H1 => "60",
+        Timeframe::H2 => "120",
+        Timeframe::H4 => "240",
+        Timeframe::H6 => "360",
+        Timeframe::H12 => "720",
+        Timeframe::D1 => "D",
+        Timeframe::W1 => "W",
+        Timeframe::MN1 => "M",
+        Timeframe::S1 | Timeframe::H8 | Timeframe::D3 => return None,
+    };
+
+    Some(interval)
 }
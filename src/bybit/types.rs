@@ -3,5 +3,7 @@ use serde_json::Value;
 
 #[derive(Deserialize)]
 pub struct BybitKlineResponse {
+    // Error responses carry an empty `result: {}`
+    #[serde(default)]
     pub list: Vec<Value>,
 }
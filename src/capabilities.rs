@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backfill::PageDirection,
+    resample::sources,
+    types::{MarketType, PriceSource, Timeframe},
+};
+
+/// Candle fields beyond OHLCV that a connection fills in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtendedField {
+    QuoteVolume,
+    Trades,
+    TakerBuyVolume,
+    TakerBuyQuoteVolume,
+}
+
+/// What a connection serves for one market type, so requests can be planned without probing the exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    pub market_type: MarketType,
+    /// Timeframes the exchange serves natively, finest first. Others may still be resampled, see [`Capabilities::source`].
+    pub timeframes: Vec<Timeframe>,
+    /// The most candles one ranged request returns.
+    pub max_page_size: usize,
+    /// How many of the most recent candles can be reached, `None` when history goes back to the listing.
+    pub max_history: Option<usize>,
+    /// [`Capabilities::max_history`] for price sources other than [`PriceSource::Last`].
+    pub price_source_max_history: Option<usize>,
+    pub extended_fields: Vec<ExtendedField>,
+    /// Prices candles can follow, see [`crate::types::CandlesRequest::price_source`].
+    pub price_sources: Vec<PriceSource>,
+    pub page_direction: PageDirection,
+    /// Whether the market is part of the symbol, like OKX `BTC-USDT-SWAP`, rather than chosen by `market_type`.
+    pub market_in_symbol: bool,
+}
+
+impl Capabilities {
+    /// Whether `timeframe` is served natively.
+    pub fn supports(&self, timeframe: &Timeframe) -> bool {
+        self.timeframes.contains(timeframe)
+    }
+
+    /// The native timeframe `timeframe` is fetched in: itself, the one it is resampled from, or `None` when unavailable.
+    pub fn source(&self, timeframe: &Timeframe) -> Option<Timeframe> {
+        match self.supports(timeframe) {
+            true => Some(timeframe.clone()),
+            false => sources(timeframe).into_iter().find(|source| self.supports(source)),
+        }
+    }
+
+    pub fn has_field(&self, field: ExtendedField) -> bool {
+        self.extended_fields.contains(&field)
+    }
+
+    pub fn has_price_source(&self, price_source: PriceSource) -> bool {
+        self.price_sources.contains(&price_source)
+    }
+
+    /// How many of the most recent `price_source` candles can be reached, `None` when history goes back to the listing.
+    pub fn history(&self, price_source: PriceSource) -> Option<usize> {
+        match price_source {
+            PriceSource::Last => self.max_history,
+            _ => self.price_source_max_history,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        backfill::PageDirection,
+        capabilities::ExtendedField,
+        connections::Connection,
+        types::{MarketType, PriceSource, Timeframe},
+    };
+
+    #[test]
+    fn test_native_and_resampled_timeframes() {
+        let mexc = Connection::Mexc.capabilities(&MarketType::Spot).unwrap();
+
+        assert!(!mexc.supports(&Timeframe::M3));
+        assert_eq!(mexc.source(&Timeframe::M3), Some(Timeframe::M1));
+        assert_eq!(mexc.source(&Timeframe::H4), Some(Timeframe::H4));
+        assert_eq!(mexc.source(&Timeframe::S1), None);
+        assert_eq!(mexc.max_page_size, 1000);
+
+        // Contract klines add an 8 hour interval
+        assert!(Connection::Mexc.capabilities(&MarketType::Derivatives).unwrap().supports(&Timeframe::H8));
+    }
+
+    #[test]
+    fn test_market_specific_details() {
+        let binance = Connection::Binance.capabilities(&MarketType::Derivatives).unwrap();
+        assert!(!binance.supports(&Timeframe::S1));
+        assert_eq!(binance.max_page_size, 1500);
+        assert!(binance.has_field(ExtendedField::TakerBuyVolume));
+
+        let okx = Connection::OKX.capabilities(&MarketType::Spot).unwrap();
+        assert!(okx.market_in_symbol);
+        assert_eq!(okx.page_direction, PageDirection::Backward);
+
+        assert_eq!(Connection::HTX.capabilities(&MarketType::Spot).unwrap().max_history, Some(2000));
+        assert_eq!(Connection::HTX.capabilities(&MarketType::Derivatives).unwrap().max_history, None);
+        assert_eq!(Connection::HTX.capabilities(&MarketType::Derivatives).unwrap().history(PriceSource::Mark), Some(2000));
+        assert!(!Connection::BingX.capabilities(&MarketType::Derivatives).unwrap().has_field(ExtendedField::QuoteVolume));
+
+        // Index candles come from Binance, OKX and Bybit only, OKX serving the index of spot pairs as well
+        assert!(Connection::OKX.capabilities(&MarketType::Spot).unwrap().has_price_source(PriceSource::Index));
+        assert!(Connection::Bybit.capabilities(&MarketType::Inverse).unwrap().has_price_source(PriceSource::Index));
+        assert!(!Connection::Binance.capabilities(&MarketType::Spot).unwrap().has_price_source(PriceSource::Index));
+        assert!(!Connection::HTX.capabilities(&MarketType::Derivatives).unwrap().has_price_source(PriceSource::Index));
+    }
+
+    #[test]
+    fn test_contract_markets() {
+        let binance = Connection::Binance.capabilities(&MarketType::Inverse).unwrap();
+        assert!(binance.has_field(ExtendedField::TakerBuyVolume));
+        assert!(!binance.has_field(ExtendedField::QuoteVolume));
+
+        // Option candles come from Binance and OKX only
+        let options = Connection::Binance.capabilities(&MarketType::Options).unwrap();
+        assert!(!options.supports(&Timeframe::H8));
+        assert_eq!(options.source(&Timeframe::MN1), Some(Timeframe::D1));
+        assert!(Connection::OKX.capabilities(&MarketType::Options).is_some());
+        assert!(Connection::Bybit.capabilities(&MarketType::Options).is_none());
+        assert!(Connection::HTX.capabilities(&MarketType::Options).is_none());
+
+        for connection in [Connection::BloFin, Connection::BingX, Connection::Mexc] {
+            assert!(connection.capabilities(&MarketType::Inverse).is_none(), "{connection}");
+            assert!(connection.capabilities(&MarketType::Delivery).is_none(), "{connection}");
+        }
+    }
+
+    #[test]
+    fn test_every_connection_serves_common_timeframes() {
+        for connection in [
+            Connection::Binance,
+            Connection::OKX,
+            Connection::Bybit,
+            Connection::BloFin,
+            Connection::BingX,
+            Connection::HTX,
+            Connection::Mexc,
+        ] {
+            for market_type in [MarketType::Spot, MarketType::Derivatives] {
+                let capabilities = connection.capabilities(&market_type).unwrap();
+
+                assert!(capabilities.max_page_size > 0, "{connection} {market_type}");
+                for timeframe in [Timeframe::M1, Timeframe::H1, Timeframe::D1] {
+                    assert!(capabilities.supports(&timeframe), "{connection} {market_type} {timeframe}");
+                }
+                assert!(capabilities.has_price_source(PriceSource::Last), "{connection} {market_type}");
+            }
+
+            // Every derivatives market publishes mark price candles, no spot market does
+            assert!(
+                connection.capabilities(&MarketType::Derivatives).unwrap().has_price_source(PriceSource::Mark),
+                "{connection}"
+            );
+            assert!(!connection.capabilities(&MarketType::Spot).unwrap().has_price_source(PriceSource::Mark), "{connection}");
+        }
+    }
+}
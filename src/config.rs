@@ -0,0 +1,192 @@
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use reqwest::{
+    Client, Response, StatusCode,
+    header::{RETRY_AFTER, USER_AGENT},
+};
+use serde::de::DeserializeOwned;
+
+use crate::{errors::CandlesError, rate_limit::RateLimiter, retry::RetryPolicy, types::MarketType};
+
+/// How much of an unparseable response body is kept in the error
+const MAX_ERROR_BODY_LEN: usize = 1024;
+
+static SHARED_HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Per-instance settings for an exchange client.
+///
+/// The default shares one `reqwest::Client` (and its connection pool) across every client in the process.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub http: Client,
+    /// Replaces the exchange's default host for every market type, e.g. `http://127.0.0.1:8080`
+    pub base_url: Option<String>,
+    /// Replaces the host for a single market type, taking precedence over `base_url`.
+    /// Needed where an exchange serves markets from separate hosts, e.g. Binance spot and futures testnets.
+    pub market_base_urls: HashMap<MarketType, String>,
+    pub timeout: Option<Duration>,
+    pub user_agent: Option<String>,
+    pub retry: RetryPolicy,
+    /// Queue requests behind the exchange's rate limiter, shared by every client of the same host
+    pub rate_limit: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            http: SHARED_HTTP_CLIENT.get_or_init(Client::new).clone(),
+            base_url: None,
+            market_base_urls: HashMap::new(),
+            timeout: Some(Duration::from_secs(30)),
+            user_agent: None,
+            retry: RetryPolicy::default(),
+            rate_limit: true,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_market_base_url(mut self, market_type: MarketType, base_url: impl Into<String>) -> Self {
+        self.market_base_urls.insert(market_type, base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: bool) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// The configured host for `market_type`, or the exchange's `default` one.
+    pub(crate) fn base_url<'a>(&'a self, market_type: &MarketType, default: &'a str) -> &'a str {
+        self.market_base_urls.get(market_type).or(self.base_url.as_ref()).map(String::as_str).unwrap_or(default)
+    }
+
+    pub(crate) async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
+        let mut builder = self.http.get(url);
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.header(USER_AGENT, user_agent);
+        }
+
+        builder.send().await
+    }
+
+    /// Sends a GET request costing `weight` against `limiter`'s budget for the url's host and deserializes the body, mapping HTTP failures to typed errors.
+    pub(crate) async fn get_json<T: DeserializeOwned>(&self, url: &str, limiter: &'static RateLimiter, weight: u32) -> Result<T, CandlesError> {
+        self.fetch_json(url, limiter, weight, false).await
+    }
+
+    /// Like [`Self::get_json`] for APIs that wrap every response in an envelope carrying their own error code.
+    /// A failed status whose body still parses as the envelope is returned, so the exchange's code can be surfaced.
+    pub(crate) async fn get_envelope<T: DeserializeOwned>(&self, url: &str, limiter: &'static RateLimiter, weight: u32) -> Result<T, CandlesError> {
+        self.fetch_json(url, limiter, weight, true).await
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(&self, url: &str, limiter: &'static RateLimiter, weight: u32, envelope: bool) -> Result<T, CandlesError> {
+        let limiter = limiter.for_host(url);
+        let mut attempt = 1;
+
+        loop {
+            if self.rate_limit {
+                limiter.acquire(weight).await;
+            }
+
+            match self.fetch_json_once(url, &limiter, envelope).await {
+                Err(error) => match self.retry.delay(attempt, &error) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch_json_once<T: DeserializeOwned>(&self, url: &str, limiter: &RateLimiter, envelope: bool) -> Result<T, CandlesError> {
+        let response = self.get(url).await?;
+        let status = response.status();
+
+        limiter.observe(response.headers());
+
+        // Binance answers 418 once an IP is banned for ignoring 429s
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs);
+
+            if let Some(retry_after) = retry_after {
+                limiter.pause(retry_after);
+            }
+
+            return Err(match status {
+                StatusCode::IM_A_TEAPOT => CandlesError::IpBanned { retry_after },
+                _ => CandlesError::RateLimited { retry_after },
+            });
+        }
+
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return match envelope {
+                true => serde_json::from_str(&body).map_err(|_| CandlesError::HttpStatus { status: status.as_u16(), body }),
+                false => Err(CandlesError::HttpStatus { status: status.as_u16(), body }),
+            };
+        }
+
+        serde_json::from_str(&body).map_err(|e| {
+            let raw: String = body.chars().take(MAX_ERROR_BODY_LEN).collect();
+            CandlesError::parse("response body", 0, format!("{e}: {raw}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::ClientConfig, types::MarketType};
+
+    #[test]
+    fn test_base_url_resolution() {
+        let config = ClientConfig::default();
+        assert_eq!(config.base_url(&MarketType::Spot, "https://default"), "https://default");
+
+        let config = config.with_base_url("http://127.0.0.1:8080/");
+        assert_eq!(config.base_url(&MarketType::Spot, "https://default"), "http://127.0.0.1:8080");
+
+        let config = config.with_market_base_url(MarketType::Derivatives, "https://testnet");
+        assert_eq!(config.base_url(&MarketType::Spot, "https://default"), "http://127.0.0.1:8080");
+        assert_eq!(config.base_url(&MarketType::Derivatives, "https://default"), "https://testnet");
+    }
+}
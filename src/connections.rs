@@ -1,17 +1,23 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
     binance::main::Binance,
     bingx::main::BingX,
     blofin::main::BloFin,
     bybit::main::Bybit,
+    capabilities::Capabilities,
+    config::ClientConfig,
     errors::CandlesError,
     htx::main::HTX,
     mexc::main::Mexc,
     okx::main::OKX,
-    types::{Candle, Instrument},
+    series::CandleSeries,
+    types::{Candle, CandlesRequest, MarketType},
 };
 
 #[derive(Hash, PartialEq, Eq, Debug, Display, EnumString, Serialize, Deserialize, Clone)]
@@ -28,15 +34,46 @@ pub enum Connection {
 }
 
 impl Connection {
-    pub async fn get_candles(&self, instrument: Instrument) -> Result<Vec<Candle>, CandlesError> {
+    /// A client for this exchange with the default configuration.
+    pub fn client(&self) -> Arc<dyn ExchangeClient> {
+        self.client_with(ClientConfig::default())
+    }
+
+    pub fn client_with(&self, config: ClientConfig) -> Arc<dyn ExchangeClient> {
         match self {
-            Connection::Binance => Binance::get_candles(instrument).await,
-            Connection::OKX => OKX::get_candles(instrument).await,
-            Connection::BloFin => BloFin::get_candles(instrument).await,
-            Connection::Bybit => Bybit::get_candles(instrument).await,
-            Connection::BingX => BingX::get_candles(instrument).await,
-            Connection::HTX => HTX::get_candles(instrument).await,
-            Connection::Mexc => Mexc::get_candles(instrument).await,
+            Connection::Binance => Arc::new(Binance::new(config)),
+            Connection::OKX => Arc::new(OKX::new(config)),
+            Connection::BloFin => Arc::new(BloFin::new(config)),
+            Connection::Bybit => Arc::new(Bybit::new(config)),
+            Connection::BingX => Arc::new(BingX::new(config)),
+            Connection::HTX => Arc::new(HTX::new(config)),
+            Connection::Mexc => Arc::new(Mexc::new(config)),
         }
     }
+
+    pub async fn get_candles(&self, request: impl Into<CandlesRequest>) -> Result<Vec<Candle>, CandlesError> {
+        self.client().get_candles(request.into()).await
+    }
+
+    pub async fn get_series(&self, request: impl Into<CandlesRequest>) -> Result<CandleSeries, CandlesError> {
+        self.client().get_series(request.into()).await
+    }
+
+    /// Fetches an arbitrary range by walking the exchange's pages and stitching them together.
+    ///
+    /// The request must have a start time; the end defaults to now. The result is ascending with no
+    /// duplicate timestamps.
+    pub async fn backfill(&self, request: impl Into<CandlesRequest>) -> Result<Vec<Candle>, CandlesError> {
+        self.client().backfill(request.into()).await
+    }
+
+    pub fn page_direction(&self, market_type: &MarketType) -> PageDirection {
+        self.client().page_direction(market_type)
+    }
+
+    /// What this exchange serves for `market_type`: native timeframes, page size, history depth and extended fields.
+    /// `None` when the exchange has no such market.
+    pub fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        self.client().capabilities(market_type)
+    }
 }
@@ -0,0 +1,226 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+
+use crate::{
+    connections::Connection,
+    errors::CandlesError,
+    types::{Instrument, MarketType},
+};
+
+/// Every supported exchange settles dated futures and options at 08:00 UTC
+pub(crate) const SETTLEMENT_HOUR: u32 = 8;
+
+/// Bybit inverse futures name their month by its futures letter code
+const MONTH_CODES: [char; 12] = ['F', 'G', 'H', 'J', 'K', 'M', 'N', 'Q', 'U', 'V', 'X', 'Z'];
+
+/// Quotes recognised in concatenated symbols like `BTCUSDT_250328`, longest first
+const QUOTES: [&str; 3] = ["USDT", "USDC", "USD"];
+
+/// A dated futures contract, the parts each exchange builds its symbol from.
+///
+/// A `USD` quote means the contract is coin-margined, any other quote is the stablecoin it is margined in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeliveryContract {
+    pub base: String,
+    pub quote: String,
+    pub expiry: NaiveDate,
+}
+
+impl DeliveryContract {
+    pub fn new(base: impl Into<String>, quote: impl Into<String>, expiry: NaiveDate) -> Self {
+        Self {
+            base: base.into().to_uppercase(),
+            quote: quote.into().to_uppercase(),
+            expiry,
+        }
+    }
+
+    pub fn is_inverse(&self) -> bool {
+        self.quote == "USD"
+    }
+
+    /// When the contract settles and its last candle closes.
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expiry.and_hms_opt(SETTLEMENT_HOUR, 0, 0).unwrap_or_default().and_utc()
+    }
+
+    /// The contract's symbol on `connection`, e.g. `BTCUSDT_250328` on Binance or `BTC-USD-250328` on OKX.
+    pub fn symbol(&self, connection: &Connection) -> Result<String, CandlesError> {
+        let (base, quote, expiry) = (&self.base, &self.quote, &self.expiry);
+
+        let symbol = match connection {
+            Connection::Binance => format!("{base}{quote}_{}", expiry.format("%y%m%d")),
+            Connection::OKX => format!("{base}-{quote}-{}", expiry.format("%y%m%d")),
+            Connection::Bybit => match quote.as_str() {
+                // Inverse futures carry only the month, they expire on its last Friday
+                "USD" => format!("{base}USD{}{}", MONTH_CODES[expiry.month0() as usize], expiry.format("%y")),
+                "USDC" => format!("{base}-{}", ddmonyy(expiry)),
+                _ => format!("{base}{quote}-{}", ddmonyy(expiry)),
+            },
+            Connection::HTX => match self.is_inverse() {
+                true => format!("{base}{}", expiry.format("%y%m%d")),
+                false => format!("{base}-{quote}-{}", expiry.format("%y%m%d")),
+            },
+            Connection::BloFin | Connection::BingX | Connection::Mexc => {
+                return Err(CandlesError::UnsupportedMarketType {
+                    connection: connection.clone(),
+                    market_type: MarketType::Delivery,
+                });
+            }
+        };
+
+        Ok(symbol)
+    }
+
+    /// Reads a delivery symbol of `connection` back into its parts, `None` when `symbol` has no expiry.
+    pub fn parse(connection: &Connection, symbol: &str) -> Option<Self> {
+        let symbol = symbol.to_uppercase();
+
+        match connection {
+            Connection::Binance => {
+                let (pair, expiry) = symbol.split_once('_')?;
+                let (base, quote) = split_quote(pair)?;
+
+                Some(Self::new(base, quote, yymmdd(expiry)?))
+            }
+            Connection::OKX => dashed(&symbol),
+            Connection::Bybit => match symbol.split_once('-') {
+                Some((pair, expiry)) => {
+                    let expiry = parse_ddmonyy(expiry)?;
+
+                    match split_quote(pair) {
+                        Some((base, quote)) => Some(Self::new(base, quote, expiry)),
+                        None => Some(Self::new(pair, "USDC", expiry)),
+                    }
+                }
+                None => {
+                    let (pair, year) = symbol.split_at_checked(symbol.len().checked_sub(2)?)?;
+                    let month = pair.chars().last().and_then(|code| MONTH_CODES.iter().position(|&c| c == code))?;
+                    let base = pair[..pair.len() - 1].strip_suffix("USD").filter(|base| !base.is_empty())?;
+
+                    Some(Self::new(base, "USD", last_friday(2000 + year.parse::<i32>().ok()?, month as u32 + 1)?))
+                }
+            },
+            Connection::HTX => match symbol.contains('-') {
+                true => dashed(&symbol),
+                false => {
+                    let (base, expiry) = symbol.split_at(symbol.find(|c: char| c.is_ascii_digit())?);
+
+                    match base.is_empty() {
+                        true => None,
+                        false => Some(Self::new(base, "USD", yymmdd(expiry)?)),
+                    }
+                }
+            },
+            Connection::BloFin | Connection::BingX | Connection::Mexc => None,
+        }
+    }
+
+    /// The contract an instrument of [`MarketType::Delivery`] refers to. A symbol without an expiry names no delivery
+    /// contract, so it is reported as not found.
+    pub(crate) fn of(instrument: &Instrument) -> Result<Self, CandlesError> {
+        Self::parse(&instrument.connection, &instrument.pair).ok_or_else(|| CandlesError::SymbolNotFound {
+            connection: instrument.connection.clone(),
+            symbol: instrument.pair.clone(),
+        })
+    }
+}
+
+/// `BASE-QUOTE-YYMMDD`, as used by OKX and HTX linear futures
+fn dashed(symbol: &str) -> Option<DeliveryContract> {
+    let mut parts = symbol.split('-');
+    let (base, quote, expiry) = (parts.next()?, parts.next()?, parts.next()?);
+
+    match parts.next() {
+        None if !base.is_empty() && !quote.is_empty() => Some(DeliveryContract::new(base, quote, yymmdd(expiry)?)),
+        _ => None,
+    }
+}
+
+fn split_quote(pair: &str) -> Option<(&str, &str)> {
+    QUOTES
+        .iter()
+        .find_map(|quote| pair.strip_suffix(quote).filter(|base| !base.is_empty()).map(|base| (base, *quote)))
+}
+
+pub(crate) fn yymmdd(expiry: &str) -> Option<NaiveDate> {
+    match expiry.len() {
+        6 => NaiveDate::parse_from_str(expiry, "%y%m%d").ok(),
+        _ => None,
+    }
+}
+
+/// Bybit's `28MAR25`
+pub(crate) fn ddmonyy(expiry: &NaiveDate) -> String {
+    expiry.format("%d%b%y").to_string().to_uppercase()
+}
+
+pub(crate) fn parse_ddmonyy(expiry: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(expiry, "%d%b%y").ok()
+}
+
+fn last_friday(year: i32, month: u32) -> Option<NaiveDate> {
+    let mut day = NaiveDate::from_ymd_opt(year, month, 1)?.checked_add_months(Months::new(1))? - Days::new(1);
+
+    while day.weekday() != Weekday::Fri {
+        day = day - Days::new(1);
+    }
+
+    Some(day)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::{connections::Connection, delivery::DeliveryContract, errors::CandlesError};
+
+    fn march_28() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 28).unwrap()
+    }
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let cases = [
+            (Connection::Binance, "USDT", "BTCUSDT_250328"),
+            (Connection::Binance, "USD", "BTCUSD_250328"),
+            (Connection::OKX, "USDT", "BTC-USDT-250328"),
+            (Connection::OKX, "USD", "BTC-USD-250328"),
+            (Connection::Bybit, "USDT", "BTCUSDT-28MAR25"),
+            (Connection::Bybit, "USDC", "BTC-28MAR25"),
+            (Connection::Bybit, "USD", "BTCUSDH25"),
+            (Connection::HTX, "USD", "BTC250328"),
+            (Connection::HTX, "USDT", "BTC-USDT-250328"),
+        ];
+
+        for (connection, quote, symbol) in cases {
+            let contract = DeliveryContract::new("btc", quote, march_28());
+
+            assert_eq!(contract.symbol(&connection).unwrap(), symbol);
+            assert_eq!(DeliveryContract::parse(&connection, symbol), Some(contract), "{connection} {symbol}");
+        }
+    }
+
+    #[test]
+    fn test_perpetual_symbols_have_no_expiry() {
+        assert_eq!(DeliveryContract::parse(&Connection::Binance, "BTCUSDT"), None);
+        assert_eq!(DeliveryContract::parse(&Connection::OKX, "BTC-USDT-SWAP"), None);
+        assert_eq!(DeliveryContract::parse(&Connection::Bybit, "BTCUSDT"), None);
+        assert_eq!(DeliveryContract::parse(&Connection::HTX, "BTC-USDT"), None);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let contract = DeliveryContract::parse(&Connection::Binance, "ETHUSD_250627").unwrap();
+
+        assert!(contract.is_inverse());
+        assert_eq!(contract.expires_at().to_rfc3339(), "2025-06-27T08:00:00+00:00");
+        // Bybit inverse month codes expire on the month's last Friday
+        assert_eq!(
+            DeliveryContract::parse(&Connection::Bybit, "BTCUSDZ24").unwrap().expiry,
+            NaiveDate::from_ymd_opt(2024, 12, 27).unwrap()
+        );
+
+        let result = DeliveryContract::new("BTC", "USDT", march_28()).symbol(&Connection::Mexc);
+        assert!(matches!(result, Err(CandlesError::UnsupportedMarketType { connection: Connection::Mexc, .. })));
+    }
+}
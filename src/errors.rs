@@ -1,10 +1,66 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
+use crate::{
+    connections::Connection,
+    types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+};
+
 #[derive(Error, Debug)]
 pub enum CandlesError {
     #[error("Connection not found: {0}")]
     ConnectionNotFound(String),
 
+    #[error("{timeframe} timeframe is not available for {connection}")]
+    UnsupportedTimeframe { connection: Connection, timeframe: Timeframe },
+
+    #[error("{market_type} market is not available for {connection}")]
+    UnsupportedMarketType { connection: Connection, market_type: MarketType },
+
+    #[error("{price_source} price candles are not available for {connection} {market_type}")]
+    UnsupportedPriceSource {
+        connection: Connection,
+        market_type: MarketType,
+        price_source: PriceSource,
+    },
+
+    /// The request itself is inconsistent, such as a backfill without a start time
+    #[error("Invalid request: {reason}")]
+    InvalidRequest { reason: String },
+
+    #[error("Symbol {symbol} not found on {connection}")]
+    SymbolNotFound { connection: Connection, symbol: String },
+
+    /// Raised by endpoints that only serve the most recent candles, see [`crate::capabilities::Capabilities::max_history`]
+    #[error("{connection} {market_type} only serves the latest {max_history} candles, the requested range reaches further back")]
+    RangeOutOfHistory {
+        connection: Connection,
+        market_type: MarketType,
+        max_history: usize,
+    },
+
+    #[error("Rate limited by the exchange{}", format_retry_after(retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// Binance's 418: the IP is banned for ignoring rate limits, and every further request extends the ban
+    #[error("IP banned by the exchange{}", format_retry_after(retry_after))]
+    IpBanned { retry_after: Option<Duration> },
+
+    #[error("API request failed with status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+
+    #[error("Exchange error {code}: {message}")]
+    ExchangeError { code: String, message: String },
+
+    /// `index` is the candle's position in the response, 0 when the response as a whole could not be parsed
+    #[error("Failed to parse {field} at index {index}: {raw}")]
+    Parse { field: String, index: usize, raw: String },
+
+    /// Stored data written by a newer release, see [`crate::schema`]
+    #[error("Schema version {version} is newer than the supported version {supported}")]
+    UnsupportedSchemaVersion { version: u32, supported: u32 },
+
     #[error("Failed to fetch candles from API: {0}")]
     ApiError(String),
 
@@ -14,3 +70,25 @@ pub enum CandlesError {
     #[error("{0}")]
     Other(String),
 }
+
+impl CandlesError {
+    pub(crate) fn unsupported_price_source(connection: Connection, request: &CandlesRequest) -> Self {
+        CandlesError::UnsupportedPriceSource {
+            connection,
+            market_type: request.instrument.market_type.clone(),
+            price_source: request.price_source,
+        }
+    }
+
+    pub(crate) fn parse(field: &str, index: usize, raw: impl ToString) -> Self {
+        CandlesError::Parse {
+            field: field.to_string(),
+            index,
+            raw: raw.to_string(),
+        }
+    }
+}
+
+fn format_retry_after(retry_after: &Option<Duration>) -> String {
+    retry_after.map(|d| format!(", retry after {}s", d.as_secs())).unwrap_or_default()
+}
@@ -1,8 +1,335 @@
 #[cfg(test)]
 mod test {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, Request, ResponseTemplate,
+        matchers::{path, query_param},
+    };
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
+        connections::Connection,
+        errors::CandlesError,
+        htx::main::HTX,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, fixture, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/market/history/kline"))
+            .and(query_param("symbol", "btcusdt"))
+            .and(query_param("period", "60min"));
+        let server = serve(mock, "htx/spot_kline.json").await;
+
+        let candles = HTX::new(config(&server))
+            .get_candles(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        assert_eq!(candles.iter().map(|c| c.trades).collect::<Vec<_>>(), [Some(10234), Some(8123), Some(4012)]);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/linear-swap-ex/market/history/kline"))
+            .and(query_param("contract_code", "BTC-USDT"))
+            .and(query_param("period", "60min"));
+        let server = serve(mock, "htx/swap_kline.json").await;
+
+        let candles = HTX::new(config(&server))
+            .get_candles(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        assert_eq!(candles.iter().map(|c| c.trades).collect::<Vec<_>>(), [Some(10234), Some(8123), Some(4012)]);
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let mock = Mock::given(path("/swap-ex/market/history/kline"))
+            .and(query_param("contract_code", "BTC-USD"))
+            .and(query_param("period", "60min"));
+        let server = serve(mock, "htx/swap_kline.json").await;
+
+        let candles = HTX::new(config(&server))
+            .get_candles(instrument(Connection::HTX, "BTC-USD", MarketType::Inverse).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_candles() {
+        let server = serve(Mock::given(path("/market/history/kline")).and(query_param("symbol", "BTC250328")), "htx/swap_kline.json").await;
+        Mock::given(path("/linear-swap-ex/market/history/kline"))
+            .and(query_param("contract_code", "BTC-USDT-250328"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(fixture("htx/swap_kline.json"), "application/json"))
+            .mount(&server)
+            .await;
+        let htx = HTX::new(config(&server));
+
+        // Coin-margined futures are looked up by symbol, USDT-margined ones alongside the swaps
+        for pair in ["BTC250328", "BTC-USDT-250328"] {
+            let candles = htx.get_candles(instrument(Connection::HTX, pair, MarketType::Delivery).into()).await.unwrap();
+
+            assert_candles(&candles, &EXPECTED_CANDLES);
+            assert_quote_volumes(&candles);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/linear-swap-ex/market/history/kline"))
+            .and(query_param("from", "1710000000"))
+            .and(query_param("to", "1710007200"));
+        let server = serve(mock, "htx/swap_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&HTX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_caps_window_to_page_size() {
+        let server = MockServer::start().await;
+
+        // Answers any window of up to 2000 hourly bars with a bar per hour, and rejects wider ones
+        Mock::given(path("/linear-swap-ex/market/history/kline"))
+            .respond_with(|request: &Request| {
+                let param = |name: &str| {
+                    request
+                        .url
+                        .query_pairs()
+                        .find(|(key, _)| key == name)
+                        .and_then(|(_, value)| value.parse::<i64>().ok())
+                        .unwrap()
+                };
+                let (from, to) = (param("from"), param("to"));
+
+                let body = match (to - from) / 3600 < 2000 {
+                    true => {
+                        let bars: Vec<_> = ((from + 3599) / 3600..=to / 3600)
+                            .map(|hour| json!({"id": hour * 3600, "open": 1.0, "close": 1.0, "low": 1.0, "high": 1.0, "amount": 1.0}))
+                            .collect();
+                        json!({"status": "ok", "data": bars})
+                    }
+                    false => json!({"status": "error", "err-code": "invalid-parameter", "err-msg": "too many bars", "data": null}),
+                };
+
+                ResponseTemplate::new(200).set_body_json(body)
+            })
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let start = 1704067200000;
+        let end = start + 2499 * 3_600_000;
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives))
+            .with_start(start)
+            .with_end(end);
+
+        let candles = HTX::new(config(&server)).backfill(request).await.unwrap();
+
+        assert_eq!(candles.len(), 2500);
+        assert_eq!((candles[0].timestamp, candles[2499].timestamp), (start, end));
+        assert!(candles.windows(2).all(|pair| pair[1].timestamp - pair[0].timestamp == 3_600_000));
+    }
+
+    #[tokio::test]
+    async fn test_spot_range_is_filtered_locally() {
+        let server = serve(Mock::given(path("/market/history/kline")), "htx/spot_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot)).with_start(1710003600000);
+
+        assert_candles(&HTX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES[1..]);
+    }
+
+    /// The latest 2000 hourly bars up to 1710007200, as range-less klines return them
+    fn latest_bars(price: serde_json::Value, newest_first: bool) -> serde_json::Value {
+        let mut bars: Vec<_> = (0..2000)
+            .map(|hour| 1710007200 - hour * 3600)
+            .map(|id| json!({"id": id, "open": price, "close": price, "low": price, "high": price, "amount": price}))
+            .collect();
+        if !newest_first {
+            bars.reverse();
+        }
+
+        json!({"status": "ok", "data": bars})
+    }
+
+    #[tokio::test]
+    async fn test_spot_range_out_of_history() {
+        let server = MockServer::start().await;
+        Mock::given(path("/market/history/kline"))
+            .and(query_param("size", "2000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(latest_bars(json!(1.0), true)))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let htx = HTX::new(config(&server));
+        let oldest = (1710007200 - 1999 * 3600) * 1000;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot)).with_start(oldest);
+        assert_eq!(htx.get_candles(request).await.unwrap().len(), 2000);
+
+        // One bar further back than the exchange serves
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot)).with_start(oldest - 3_600_000);
+        let result = htx.get_candles(request).await;
+
+        assert!(matches!(
+            result,
+            Err(CandlesError::RangeOutOfHistory {
+                market_type: MarketType::Spot,
+                max_history: 2000,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_backfill_out_of_history() {
+        let server = MockServer::start().await;
+        Mock::given(path("/index/market/history/linear_swap_mark_price_kline"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(latest_bars(json!("1"), false)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives))
+            .with_start(1700000000000)
+            .with_end(1710007200000)
+            .with_price_source(PriceSource::Mark);
+        let result = HTX::new(config(&server)).backfill(request).await;
+
+        assert!(matches!(result, Err(CandlesError::RangeOutOfHistory { max_history: 2000, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/index/market/history/linear_swap_mark_price_kline"))
+            .and(query_param("contract_code", "BTC-USDT"))
+            .and(query_param("period", "60min"));
+        let server = serve(mock, "htx/mark_price_kline.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = HTX::new(config(&server)).get_candles(request).await.unwrap();
+
+        // Prices are quoted as strings
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert!(candles.iter().all(|c| c.quote_volume.is_none() && c.trades.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_range_is_filtered_locally() {
+        // Mark price klines take no range, so a ranged request takes every bar within reach
+        let mock = Mock::given(path("/index/market/history/swap_mark_price_kline"))
+            .and(query_param("contract_code", "BTC-USD"))
+            .and(query_param("size", "2000"));
+        let server = serve(mock, "htx/mark_price_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTC-USD", MarketType::Inverse))
+            .with_start(1710003600000)
+            .with_price_source(PriceSource::Mark);
+
+        assert_candles(&HTX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_PRICE_CANDLES[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = HTX::new(config(&server)).get_candles(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot).into()).await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_timeframe() {
+        let mut instrument = instrument(Connection::HTX, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::S1;
+
+        let result = HTX::default().get_candles(instrument.into()).await;
+
+        assert!(matches!(result, Err(CandlesError::UnsupportedTimeframe { timeframe: Timeframe::S1, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_resamples_unsupported_timeframe() {
+        let mock = Mock::given(path("/market/history/kline")).and(query_param("period", "1min"));
+        let server = serve(mock, "htx/spot_kline_1min.json").await;
+
+        let mut instrument = instrument(Connection::HTX, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::M3;
+
+        let candles = HTX::new(config(&server)).get_candles(instrument.into()).await.unwrap();
+
+        // Minutes 1-2 and 6-7 only partly cover their 3 minute candles, long closed, and are dropped
+        assert_candles(&candles, &[(1710000180000, 68010.0, 68040.0, 67990.0, 68030.0, 6.0)]);
+        assert_eq!(candles[0].trades, Some(60));
+    }
+
+    #[tokio::test]
+    async fn test_symbol_not_found() {
+        let server = serve_status(200, r#"{"status":"error","err-code":"invalid-parameter","err-msg":"invalid symbol","data":null}"#).await;
+
+        let result = HTX::new(config(&server))
+            .get_candles(instrument(Connection::HTX, "NOPEUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::SymbolNotFound { symbol, .. }) if symbol == "NOPEUSDT"));
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_exchange_error() {
+        let server = serve_status(200, r#"{"status":"error","err_code":1014,"err_msg":"This contract doesnt exist.","ts":1710008000000}"#).await;
+
+        let result = HTX::new(config(&server))
+            .get_candles(instrument(Connection::HTX, "NOPE-USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::ExchangeError { code, .. }) if code == "1014"));
+    }
+
+    #[tokio::test]
+    async fn test_size_follows_limit() {
+        let mock = Mock::given(path("/market/history/kline")).and(query_param("size", "2000"));
+        let server = serve(mock, "htx/spot_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTCUSDT", MarketType::Spot)).with_limit(2000);
+
+        assert_candles(&HTX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_window_from_end_only() {
+        // 10 hourly periods before the end
+        let mock = Mock::given(path("/linear-swap-ex/market/history/kline"))
+            .and(query_param("from", "1709971200"))
+            .and(query_param("to", "1710007200"));
+        let server = serve(mock, "htx/swap_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::HTX, "BTC-USDT", MarketType::Derivatives))
+            .with_end(1710007200000)
+            .with_limit(10);
+
+        assert_eq!(HTX::new(config(&server)).get_candles(request).await.unwrap().len(), 3);
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
+
+    use crate::{
+        base::ExchangeClient,
         connections::Connection,
         htx::main::HTX,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +346,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match HTX::get_candles(instrument).await {
+        match HTX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +362,23 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match HTX::get_candles(instrument).await {
+        match HTX::default().get_candles(instrument.into()).await {
+            Ok(result) => examine_candles(&result),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let instrument = Instrument {
+            asset_id: "bitcoin".to_owned(),
+            pair: "BTC-USD".to_owned(),
+            connection: Connection::HTX,
+            market_type: MarketType::Inverse,
+            timeframe: Timeframe::H1,
+        };
+
+        match HTX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -1,58 +1,305 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
+    delivery::DeliveryContract,
     errors::CandlesError,
-    htx::types::HtxKlineResponse,
-    types::{Candle, Instrument, MarketType, Timeframe},
-    utils::DataWrapper,
+    htx::types::{HtxKlineResponse, HtxMarkPriceKline},
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, Instrument, MarketType, PriceSource, Timeframe},
+    utils::{DataWrapperWithStatus, map_symbol_not_found, mark_closed},
 };
 
-pub struct HTX;
+pub const SPOT_BASE_URL: &str = "https://api.huobi.pro";
+pub const DERIVATIVES_BASE_URL: &str = "https://api.hbdm.com";
+
+const INVALID_PARAMETER_CODE: &str = "invalid-parameter";
+const DEFAULT_SIZE: usize = 1000;
+const MAX_SIZE: usize = 2000;
+
+// Documented per-IP limit for public market data: 800 requests per second on both hosts
+static SPOT_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(800, Duration::from_secs(1))));
+static DERIVATIVES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(800, Duration::from_secs(1))));
+
+#[derive(Debug, Clone, Default)]
+pub struct HTX {
+    config: ClientConfig,
+}
+
+impl HTX {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for HTX {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, crate::errors::CandlesError> {
-        let htx_timeframe = match instrument.timeframe {
-            Timeframe::M3 => return Err(CandlesError::Other("m3 Timeframe is not available for HTX".to_string())),
-            Timeframe::M5 => "5min",
-            Timeframe::M15 => "15min",
-            Timeframe::M30 => "30min",
-            Timeframe::H1 => "60min",
-            Timeframe::H4 => "4hour",
-            Timeframe::D1 => "1day",
-            Timeframe::W1 => "1week",
-            Timeframe::MN1 => "1mon",
-        };
+impl ExchangeClient for HTX {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        if instrument.market_type == MarketType::Options {
+            return Err(CandlesError::UnsupportedMarketType {
+                connection: Connection::HTX,
+                market_type: instrument.market_type.clone(),
+            });
+        }
+
+        let htx_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::HTX,
+            timeframe: instrument.timeframe.clone(),
+        })?;
+
+        let size = request.limit.unwrap_or(DEFAULT_SIZE).min(MAX_SIZE);
+
+        match (request.price_source, &instrument.market_type) {
+            (PriceSource::Last, _) => {}
+            (PriceSource::Mark, MarketType::Derivatives | MarketType::Inverse | MarketType::Delivery) => {
+                return self.get_mark_price_page(&request, htx_timeframe, latest_size(&request, size)).await;
+            }
+            _ => return Err(CandlesError::unsupported_price_source(Connection::HTX, &request)),
+        }
 
         let url = match instrument.market_type {
             MarketType::Spot => format!(
-                "https://api.huobi.pro/market/history/kline?symbol={}&period={}&size=1000",
+                "{}/market/history/kline?symbol={}&period={}&size={}",
+                self.config.base_url(&instrument.market_type, SPOT_BASE_URL),
                 instrument.pair.to_lowercase(),
-                htx_timeframe
-            ),
-            MarketType::Derivatives => format!(
-                "https://api.hbdm.com/linear-swap-ex/market/history/kline?contract_code={}&period={}&size=1000",
-                instrument.pair, htx_timeframe
+                htx_timeframe,
+                latest_size(&request, size)
             ),
+            // Contract klines take the range in seconds and ignore `size` when `from`/`to` are given
+            _ => match request.has_range() {
+                true => {
+                    let to = request.end.unwrap_or_else(|| Utc::now().timestamp_millis()) / 1000;
+                    // Months are counted at their longest
+                    let step = instrument.timeframe.duration().unwrap_or(TimeDelta::days(31)).num_seconds();
+
+                    // One query spans at most MAX_SIZE bars, so a wider range is cut short and the rest left to the next page
+                    let (from, to) = match request.start {
+                        Some(start) => (start / 1000, to.min(start / 1000 + (MAX_SIZE as i64 - 1) * step)),
+                        None => (to - size as i64 * step, to),
+                    };
+
+                    format!(
+                        "{}/{}={}&period={}&from={}&to={}",
+                        self.config.base_url(&instrument.market_type, DERIVATIVES_BASE_URL),
+                        contract_kline(instrument)?,
+                        instrument.pair,
+                        htx_timeframe,
+                        from,
+                        to
+                    )
+                }
+                false => format!(
+                    "{}/{}={}&period={}&size={}",
+                    self.config.base_url(&instrument.market_type, DERIVATIVES_BASE_URL),
+                    contract_kline(instrument)?,
+                    instrument.pair,
+                    htx_timeframe,
+                    size
+                ),
+            },
         };
 
-        let response: DataWrapper<Vec<HtxKlineResponse>> = reqwest::get(&url).await?.json().await?;
+        let limiter = match instrument.market_type {
+            MarketType::Spot => &*SPOT_LIMITER,
+            _ => &*DERIVATIVES_LIMITER,
+        };
+
+        let response: DataWrapperWithStatus<Vec<HtxKlineResponse>> = self.config.get_envelope(&url, limiter, 1).await?;
+        let data = response.into_data().map_err(|e| {
+            map_symbol_not_found(e, Connection::HTX, &instrument.pair, |code, message| {
+                code == INVALID_PARAMETER_CODE && message.contains("symbol")
+            })
+        })?;
 
         let iterator: Box<dyn Iterator<Item = _>> = match instrument.market_type {
-            MarketType::Spot => Box::new(response.data.into_iter().rev()),
-            MarketType::Derivatives => Box::new(response.data.into_iter()),
+            MarketType::Spot => Box::new(data.into_iter().rev()),
+            _ => Box::new(data.into_iter()),
         };
 
-        Ok(iterator
+        let mut candles: Vec<Candle> = iterator
             .map(|f| Candle {
-                timestamp: f.id,
+                // HTX reports open times in seconds
+                timestamp: f.id * 1000,
                 open: f.open,
                 high: f.high,
                 low: f.low,
                 close: f.close,
                 volume: f.amount,
+                quote_volume: match instrument.market_type {
+                    MarketType::Spot => f.vol,
+                    _ => f.trade_turnover,
+                },
+                trades: f.count,
+                ..Default::default()
             })
-            .collect())
+            .collect();
+
+        // Spot klines have no range parameters, so the latest page is filtered locally
+        if instrument.market_type == MarketType::Spot {
+            check_history(&request, &candles)?;
+        }
+        candles.retain(|c| request.contains(c.timestamp));
+
+        mark_closed(&mut candles, &instrument.timeframe);
+
+        Ok(candles)
+    }
+
+    fn max_page_size(&self, _request: &CandlesRequest) -> usize {
+        MAX_SIZE
     }
+
+    fn page_direction(&self, market_type: &MarketType) -> PageDirection {
+        match market_type {
+            MarketType::Spot => PageDirection::Backward,
+            _ => PageDirection::Forward,
+        }
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        if *market_type == MarketType::Options {
+            return None;
+        }
+
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: MAX_SIZE,
+            // Spot and mark price klines take no range, only the most recent `size`
+            max_history: match market_type {
+                MarketType::Spot => Some(MAX_SIZE),
+                _ => None,
+            },
+            price_source_max_history: match market_type {
+                MarketType::Spot => None,
+                _ => Some(MAX_SIZE),
+            },
+            extended_fields: vec![ExtendedField::QuoteVolume, ExtendedField::Trades],
+            price_sources: match market_type {
+                MarketType::Spot => vec![PriceSource::Last],
+                _ => vec![PriceSource::Last, PriceSource::Mark],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: false,
+        })
+    }
+}
+
+impl HTX {
+    /// Mark price klines take no range, so like spot the latest `size` bars are fetched and filtered locally.
+    async fn get_mark_price_page(&self, request: &CandlesRequest, htx_timeframe: &str, size: usize) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        let url = format!(
+            "{}/index/market/history/{}={}&period={}&size={}",
+            self.config.base_url(&instrument.market_type, DERIVATIVES_BASE_URL),
+            mark_price_kline(instrument)?,
+            instrument.pair,
+            htx_timeframe,
+            size
+        );
+
+        let response: DataWrapperWithStatus<Vec<HtxMarkPriceKline>> = self.config.get_envelope(&url, &DERIVATIVES_LIMITER, 1).await?;
+        let data = response.into_data().map_err(|e| {
+            map_symbol_not_found(e, Connection::HTX, &instrument.pair, |code, message| {
+                code == INVALID_PARAMETER_CODE && message.contains("symbol")
+            })
+        })?;
+
+        let mut candles: Vec<Candle> = data
+            .into_iter()
+            .map(|f| Candle {
+                timestamp: f.id * 1000,
+                open: f.open,
+                high: f.high,
+                low: f.low,
+                close: f.close,
+                ..Default::default()
+            })
+            .collect();
+
+        check_history(request, &candles)?;
+        candles.retain(|c| request.contains(c.timestamp));
+        candles.sort_by_key(|c| c.timestamp);
+        mark_closed(&mut candles, &instrument.timeframe);
+
+        Ok(candles)
+    }
+}
+
+/// The `size` of a range-less request: a ranged one takes every bar within reach, as the range is applied locally.
+fn latest_size(request: &CandlesRequest, size: usize) -> usize {
+    match request.has_range() {
+        true => MAX_SIZE,
+        false => size,
+    }
+}
+
+/// Fails when a full range-less page opens after the requested range, whose start (or end when open) lies beyond reach
+/// rather than before the listing.
+fn check_history(request: &CandlesRequest, candles: &[Candle]) -> Result<(), CandlesError> {
+    let oldest = candles.iter().map(|c| c.timestamp).min();
+
+    match (request.start.or(request.end), oldest) {
+        (Some(bound), Some(oldest)) if candles.len() >= MAX_SIZE && bound < oldest => Err(CandlesError::RangeOutOfHistory {
+            connection: Connection::HTX,
+            market_type: request.instrument.market_type.clone(),
+            max_history: MAX_SIZE,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The kline path and contract parameter for `instrument`. Each margin type is served under its own path of the same host,
+/// USDT-margined futures alongside the swaps.
+fn contract_kline(instrument: &Instrument) -> Result<&'static str, CandlesError> {
+    Ok(match (&instrument.market_type, coin_margined(instrument)?) {
+        (MarketType::Delivery, true) => "market/history/kline?symbol",
+        (_, true) => "swap-ex/market/history/kline?contract_code",
+        (_, false) => "linear-swap-ex/market/history/kline?contract_code",
+    })
+}
+
+/// The mark price kline path and contract parameter for `instrument`, split by margin type like [`contract_kline`].
+fn mark_price_kline(instrument: &Instrument) -> Result<&'static str, CandlesError> {
+    Ok(match (&instrument.market_type, coin_margined(instrument)?) {
+        (MarketType::Delivery, true) => "mark_price_kline?symbol",
+        (_, true) => "swap_mark_price_kline?contract_code",
+        (_, false) => "linear_swap_mark_price_kline?contract_code",
+    })
+}
+
+fn coin_margined(instrument: &Instrument) -> Result<bool, CandlesError> {
+    match instrument.market_type {
+        MarketType::Delivery => Ok(DeliveryContract::of(instrument)?.is_inverse()),
+        _ => Ok(instrument.market_type == MarketType::Inverse),
+    }
+}
+
+/// The HTX interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, _market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        Timeframe::M1 => "1min",
+        Timeframe::M5 => "5min",
+        Timeframe::M15 => "15min",
+        Timeframe::M30 => "30min",
+        Timeframe::H1 => "60min",
+        Timeframe::H4 => "4hour",
+        Timeframe::D1 => "1day",
+        Timeframe::W1 => "1week",
+        Timeframe::MN1 => "1mon",
+        Timeframe::S1 | Timeframe::M3 | Timeframe::H2 | Timeframe::H6 | Timeframe::H8 | Timeframe::H12 | Timeframe::D3 => return None,
+    };
+
+    Some(interval)
 }
@@ -1,12 +1,32 @@
 use serde::Deserialize;
 
+use crate::types::Number;
+
 #[derive(Deserialize)]
 pub struct HtxKlineResponse {
     pub id: i64,
-    pub open: f64,
+    pub open: Number,
+
+    pub close: Number,
+    pub low: Number,
+    pub high: Number,
+    pub amount: Number,
+
+    pub vol: Option<Number>,            // Spot: turnover in the quote currency. Swaps: contracts
+    pub trade_turnover: Option<Number>, // Swaps only: turnover in the quote currency, USD for coin-margined swaps
+    pub count: Option<u64>,
+}
 
-    pub close: f64,
-    pub low: f64,
-    pub high: f64,
-    pub amount: f64,
+/// Mark price klines quote their prices as strings and carry no meaningful volume
+#[derive(Deserialize)]
+pub struct HtxMarkPriceKline {
+    pub id: i64,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub open: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub close: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub low: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub high: Number,
 }
@@ -1,9 +1,22 @@
+pub mod backfill;
 pub mod base;
+pub mod capabilities;
+pub mod config;
 pub mod connections;
+pub mod delivery;
 pub mod errors;
+pub mod options;
+pub mod rate_limit;
+pub mod resample;
+pub mod retry;
+pub mod schema;
+pub mod series;
 pub mod types;
 pub mod utils;
 
+#[cfg(test)]
+mod testing;
+
 pub mod binance {
     mod __test__;
     pub mod main;
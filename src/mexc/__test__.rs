@@ -1,8 +1,195 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock,
+        matchers::{path, query_param},
+    };
+
+    use crate::{
+        base::ExchangeClient,
+        connections::Connection,
+        errors::CandlesError,
+        mexc::main::Mexc,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, EXPECTED_QUOTE_VOLUMES, assert_candles, assert_quote_volumes, config, float, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/api/v3/klines"))
+            .and(query_param("symbol", "BTCUSDT"))
+            .and(query_param("interval", "60m"));
+        let server = serve(mock, "mexc/spot_klines.json").await;
+
+        let candles = Mexc::new(config(&server))
+            .get_candles(instrument(Connection::Mexc, "BTCUSDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_candles() {
+        let mock = Mock::given(path("/api/v1/contract/kline/BTC_USDT")).and(query_param("interval", "Min60"));
+        let server = serve(mock, "mexc/contract_kline.json").await;
+
+        let candles = Mexc::new(config(&server))
+            .get_candles(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/api/v1/contract/kline/BTC_USDT"))
+            .and(query_param("start", "1710000000"))
+            .and(query_param("end", "1710007200"));
+        let server = serve(mock, "mexc/contract_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_eq!(Mexc::new(config(&server)).get_candles(request).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fair_price_candles() {
+        let mock = Mock::given(path("/api/v1/contract/kline/fair_price/BTC_USDT")).and(query_param("interval", "Min60"));
+        let server = serve(mock, "mexc/fair_price_kline.json").await;
+
+        let request = CandlesRequest::from(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = Mexc::new(config(&server)).get_candles(request).await.unwrap();
+
+        // Fair price klines have no volume columns
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert!(candles.iter().all(|c| c.quote_volume.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = Mexc::new(config(&server))
+            .get_candles(instrument(Connection::Mexc, "BTCUSDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_exchange_error() {
+        let server = serve_status(200, r#"{"success":false,"code":1001,"message":"contract not exists"}"#).await;
+
+        let result = Mexc::new(config(&server))
+            .get_candles(instrument(Connection::Mexc, "NOPE_USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::ExchangeError { code, message }) if code == "1001" && message == "contract not exists"));
+    }
+
+    #[tokio::test]
+    async fn test_inverse_unsupported() {
+        let result = Mexc::default().get_candles(instrument(Connection::Mexc, "BTC_USD", MarketType::Inverse).into()).await;
+
+        assert!(matches!(
+            result,
+            Err(CandlesError::UnsupportedMarketType {
+                market_type: MarketType::Inverse,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_index_price_unsupported() {
+        let request = CandlesRequest::from(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives)).with_price_source(PriceSource::Index);
+        let error = Mexc::default().get_candles(request).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            CandlesError::UnsupportedPriceSource {
+                price_source: PriceSource::Index,
+                ..
+            }
+        ));
+        assert_eq!(error.to_string(), "index price candles are not available for mexc derivatives");
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_series() {
+        let server = serve(Mock::given(path("/api/v1/contract/kline/BTC_USDT")), "mexc/contract_kline.json").await;
+
+        let series = Mexc::new(config(&server))
+            .get_series(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives).into())
+            .await
+            .unwrap();
+
+        assert_eq!(series.instrument.pair, "BTC_USDT");
+        assert_eq!(series.timestamps, EXPECTED_CANDLES.map(|c| c.0));
+        assert_eq!(series.open.iter().map(|n| float(*n)).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.1));
+        assert_eq!(series.high.iter().map(|n| float(*n)).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.2));
+        assert_eq!(series.low.iter().map(|n| float(*n)).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.3));
+        assert_eq!(series.close.iter().map(|n| float(*n)).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.4));
+        assert_eq!(series.volume.iter().map(|n| float(*n)).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.5));
+        assert_eq!(series.quote_volume.iter().map(|n| n.map(float)).collect::<Vec<_>>(), EXPECTED_QUOTE_VOLUMES.map(Some));
+        assert_eq!(series.is_closed, [true, true, true]);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_series_limit() {
+        let server = serve(Mock::given(path("/api/v1/contract/kline/BTC_USDT")), "mexc/contract_kline.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives))
+            .with_limit(2)
+            .with_closed_only(true);
+        let series = Mexc::new(config(&server)).get_series(request).await.unwrap();
+
+        // Every column is cut to the latest two rows
+        assert_candles(&series.into_candles(), &EXPECTED_CANDLES[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_derivatives_misaligned_columns() {
+        let body = r#"{"success":true,"code":0,"data":{"time":[1710000000,1710003600],"open":[1.0],"close":[1.0,1.0],"high":[1.0,1.0],"low":[1.0,1.0],"vol":[1.0,1.0]}}"#;
+        let server = serve_status(200, body).await;
+
+        let result = Mexc::new(config(&server))
+            .get_candles(instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::Parse { field, index: 1, .. }) if field == "open price"));
+    }
+
+    #[tokio::test]
+    async fn test_extended_timeframes() {
+        let server = serve(
+            Mock::given(path("/api/v1/contract/kline/BTC_USDT")).and(query_param("interval", "Hour8")),
+            "mexc/contract_kline.json",
+        )
+        .await;
+
+        let mut instrument = instrument(Connection::Mexc, "BTC_USDT", MarketType::Derivatives);
+        instrument.timeframe = Timeframe::H8;
+        assert_eq!(Mexc::new(config(&server)).get_candles(instrument.clone().into()).await.unwrap().len(), 3);
+
+        // Nothing finer to resample from
+        instrument.timeframe = Timeframe::S1;
+        let result = Mexc::default().get_candles(instrument.into()).await;
+        assert!(matches!(result, Err(CandlesError::UnsupportedTimeframe { timeframe: Timeframe::S1, .. })));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
         connections::Connection,
         mexc::main::Mexc,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +206,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Mexc::get_candles(instrument).await {
+        match Mexc::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +222,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match Mexc::get_candles(instrument).await {
+        match Mexc::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -1,92 +1,245 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
+use chrono::Utc;
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
     errors::CandlesError,
     mexc::types::MexcKlineFuturesResponse,
-    types::{Candle, Instrument, MarketType, Timeframe},
-    utils::{DataWrapper, parse_string_to_f64},
+    rate_limit::{RateLimit, RateLimiter},
+    series::CandleSeries,
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{DataWrapperWithStatusCode, candle_array, map_msg_code_error, mark_closed, parse_number, parse_optional_number, parse_timestamp, validate_open_times},
 };
 
-pub struct Mexc;
+pub const SPOT_BASE_URL: &str = "https://api.mexc.com";
+pub const DERIVATIVES_BASE_URL: &str = "https://contract.mexc.com";
+
+const INVALID_SYMBOL_CODE: i64 = -1121;
+const SPOT_MAX_LIMIT: usize = 1000;
+// Contract klines take no limit and return up to 2000 candles
+const DERIVATIVES_MAX_LIMIT: usize = 2000;
+
+// Documented per-IP limits: 500 requests per 10 seconds on spot klines, 20 per 2 seconds on contract klines
+static SPOT_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(500, Duration::from_secs(10))));
+static DERIVATIVES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(20, Duration::from_secs(2))));
+
+#[derive(Debug, Clone, Default)]
+pub struct Mexc {
+    config: ClientConfig,
+}
+
+impl Mexc {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Mexc {
+    /// One page of contract klines, kept in the columns Mexc sends them in.
+    async fn contract_series(&self, request: &CandlesRequest, kline: &str, mexc_timeframe: &str) -> Result<CandleSeries, CandlesError> {
+        let instrument = &request.instrument;
+
+        let mut url = format!(
+            "{}/api/v1/contract/{}/{}?interval={}",
+            self.config.base_url(&instrument.market_type, DERIVATIVES_BASE_URL),
+            kline,
+            instrument.pair,
+            mexc_timeframe
+        );
+
+        // Contract klines take the range in seconds
+        if let Some(start) = request.start {
+            url.push_str(&format!("&start={}", start / 1000));
+        }
+        if let Some(end) = request.end {
+            url.push_str(&format!("&end={}", end / 1000));
+        }
+
+        let response: DataWrapperWithStatusCode<i64, MexcKlineFuturesResponse> = self.config.get_envelope(&url, &DERIVATIVES_LIMITER, 1).await?;
+        let mut series = response.into_data("0")?.into_series(instrument.clone())?;
+
+        let now = Utc::now().timestamp_millis();
+        series.is_closed = series.timestamps.iter().map(|&timestamp| instrument.timeframe.close_time(timestamp) <= now).collect();
+
+        Ok(series)
+    }
+}
 
 #[async_trait]
-impl BaseConnection for Mexc {
-    async fn get_candles(instrument: Instrument) -> Result<Vec<Candle>, crate::errors::CandlesError> {
+impl ExchangeClient for Mexc {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        if !matches!(instrument.market_type, MarketType::Spot | MarketType::Derivatives) {
+            return Err(CandlesError::UnsupportedMarketType {
+                connection: Connection::Mexc,
+                market_type: instrument.market_type.clone(),
+            });
+        }
+
+        let mexc_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::Mexc,
+            timeframe: instrument.timeframe.clone(),
+        })?;
+
         match instrument.market_type {
+            MarketType::Spot if request.price_source != PriceSource::Last => Err(CandlesError::unsupported_price_source(Connection::Mexc, &request)),
             MarketType::Spot => {
-                let mexc_timeframe = match instrument.timeframe {
-                    Timeframe::M3 => return Err(CandlesError::Other("m3 Timeframe is not available for Mexc".to_string())),
-                    Timeframe::M5 => "5m",
-                    Timeframe::M15 => "15m",
-                    Timeframe::M30 => "30m",
-                    Timeframe::H1 => "60m",
-                    Timeframe::H4 => "4h",
-                    Timeframe::D1 => "1d",
-                    Timeframe::W1 => "1W",
-                    Timeframe::MN1 => "1M",
-                };
-
-                let url = format!("https://api.mexc.com/api/v3/klines?symbol={}&interval={}", instrument.pair, mexc_timeframe);
-
-                let response: Vec<Vec<Value>> = reqwest::get(&url).await?.json().await?;
+                let mut url = format!(
+                    "{}/api/v3/klines?symbol={}&interval={}",
+                    self.config.base_url(&instrument.market_type, SPOT_BASE_URL),
+                    instrument.pair,
+                    mexc_timeframe
+                );
+
+                if let Some(start) = request.start {
+                    url.push_str(&format!("&startTime={start}"));
+                }
+                if let Some(end) = request.end {
+                    url.push_str(&format!("&endTime={end}"));
+                }
+                if let Some(limit) = request.limit {
+                    url.push_str(&format!("&limit={}", limit.min(SPOT_MAX_LIMIT)));
+                }
+
+                let response: Vec<Value> = self
+                    .config
+                    .get_json(&url, &SPOT_LIMITER, 1)
+                    .await
+                    .map_err(|e| map_msg_code_error(e, Connection::Mexc, &instrument.pair, INVALID_SYMBOL_CODE))?;
                 let mut candles = Vec::with_capacity(response.len());
 
-                for (index, candle_array) in response.into_iter().enumerate() {
-                    if candle_array.len() < 6 {
-                        return Err(CandlesError::Other(format!(
-                            "Insufficient data in candle array at index {index}: expected at least 6 elements, got {}",
-                            candle_array.len()
-                        )));
-                    }
+                for (index, value) in response.iter().enumerate() {
+                    let candle_array = candle_array(value, index, 6)?;
 
                     candles.push(Candle {
-                        timestamp: candle_array[0]
-                            .as_i64()
-                            .ok_or_else(|| CandlesError::Other(format!("Failed to parse timestamp at index {} with value {}", index, candle_array[0])))?,
-                        open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                        high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                        low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                        close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                        volume: parse_string_to_f64(&candle_array[5], "volume", index)?,
+                        timestamp: parse_timestamp(&candle_array[0], index)?,
+                        open: parse_number(&candle_array[1], "open price", index)?,
+                        high: parse_number(&candle_array[2], "high price", index)?,
+                        low: parse_number(&candle_array[3], "low price", index)?,
+                        close: parse_number(&candle_array[4], "close price", index)?,
+                        volume: parse_number(&candle_array[5], "volume", index)?,
+                        quote_volume: parse_optional_number(candle_array.get(7), "quote volume", index)?,
+                        ..Default::default()
                     });
                 }
 
+                mark_closed(&mut candles, &instrument.timeframe);
+
                 Ok(candles)
             }
 
-            MarketType::Derivatives => {
-                let mexc_timeframe = match instrument.timeframe {
-                    Timeframe::M3 => return Err(CandlesError::Other("m3 Timeframe is not available for Mexc".to_string())),
-                    Timeframe::M5 => "Min5",
-                    Timeframe::M15 => "Min15",
-                    Timeframe::M30 => "Min30",
-                    Timeframe::H1 => "Min60",
-                    Timeframe::H4 => "Hour4",
-                    Timeframe::D1 => "Day1",
-                    Timeframe::W1 => "Week1",
-                    Timeframe::MN1 => "Month1",
-                };
-
-                let url = format!("https://contract.mexc.com/api/v1/contract/kline/{}?interval={}", instrument.pair, mexc_timeframe);
-                let response: DataWrapper<MexcKlineFuturesResponse> = reqwest::get(&url).await?.json().await?;
-
-                let mut candles = Vec::with_capacity(response.data.time.len());
-
-                for (index, timestamp) in response.data.time.into_iter().enumerate() {
-                    candles.push(Candle {
-                        timestamp,
-                        open: response.data.open[index],
-                        high: response.data.high[index],
-                        low: response.data.low[index],
-                        close: response.data.close[index],
-                        volume: response.data.vol[index],
-                    });
-                }
+            _ => Ok(self.contract_series(&request, contract_kline(&request)?, mexc_timeframe).await?.into_candles()),
+        }
+    }
 
-                Ok(candles)
+    /// Contract klines arrive as columns, so a request answered by one native page is returned as they are. Anything
+    /// else goes through [`ExchangeClient::get_candles`].
+    async fn get_series(&self, request: CandlesRequest) -> Result<CandleSeries, CandlesError> {
+        let instrument = &request.instrument;
+        // A limited range is walked back from its end by `get_candles`, contract pages fill from the start
+        let single_page = request.limit.is_none_or(|limit| limit <= DERIVATIVES_MAX_LIMIT && request.start.is_none());
+
+        let (Some(mexc_timeframe), Ok(kline), true) = (interval(&instrument.timeframe, &instrument.market_type), contract_kline(&request), single_page) else {
+            return Ok(CandleSeries::from_candles(instrument.clone(), self.get_candles(request).await?));
+        };
+
+        let mut series = self.contract_series(&request, kline, mexc_timeframe).await?;
+        validate_open_times(series.timestamps.iter().copied())?;
+
+        // Only the latest candle can still be forming
+        if request.closed_only {
+            if let Some(forming) = series.is_closed.iter().position(|closed| !closed) {
+                series = series.slice(..series.timestamps[forming]);
+            }
+        }
+        if let Some(limit) = request.limit {
+            if limit < series.len() {
+                series = series.slice(series.timestamps[series.len() - limit]..);
             }
         }
+
+        Ok(series)
     }
+
+    fn max_page_size(&self, request: &CandlesRequest) -> usize {
+        match request.instrument.market_type {
+            MarketType::Spot => SPOT_MAX_LIMIT,
+            _ => DERIVATIVES_MAX_LIMIT,
+        }
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Forward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        if !matches!(market_type, MarketType::Spot | MarketType::Derivatives) {
+            return None;
+        }
+
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            max_page_size: match market_type {
+                MarketType::Spot => SPOT_MAX_LIMIT,
+                _ => DERIVATIVES_MAX_LIMIT,
+            },
+            max_history: None,
+            price_source_max_history: None,
+            extended_fields: vec![ExtendedField::QuoteVolume],
+            price_sources: match market_type {
+                MarketType::Spot => vec![PriceSource::Last],
+                _ => vec![PriceSource::Last, PriceSource::Mark],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: false,
+        })
+    }
+}
+
+/// The contract kline path for the request's price source, `Err` on markets and sources without one.
+fn contract_kline(request: &CandlesRequest) -> Result<&'static str, CandlesError> {
+    match (request.price_source, &request.instrument.market_type) {
+        (PriceSource::Last, MarketType::Derivatives) => Ok("kline"),
+        // Mexc calls the mark price the fair price
+        (PriceSource::Mark, MarketType::Derivatives) => Ok("kline/fair_price"),
+        _ => Err(CandlesError::unsupported_price_source(Connection::Mexc, request)),
+    }
+}
+
+/// The Mexc interval for `timeframe`, `None` when it is not served. Spot and contract klines name intervals differently.
+fn interval(timeframe: &Timeframe, market_type: &MarketType) -> Option<&'static str> {
+    let interval = match (market_type, timeframe) {
+        (MarketType::Spot, Timeframe::M1) => "1m",
+        (MarketType::Spot, Timeframe::M5) => "5m",
+        (MarketType::Spot, Timeframe::M15) => "15m",
+        (MarketType::Spot, Timeframe::M30) => "30m",
+        (MarketType::Spot, Timeframe::H1) => "60m",
+        (MarketType::Spot, Timeframe::H4) => "4h",
+        (MarketType::Spot, Timeframe::D1) => "1d",
+        (MarketType::Spot, Timeframe::W1) => "1W",
+        (MarketType::Spot, Timeframe::MN1) => "1M",
+        (MarketType::Derivatives, Timeframe::M1) => "Min1",
+        (MarketType::Derivatives, Timeframe::M5) => "Min5",
+        (MarketType::Derivatives, Timeframe::M15) => "Min15",
+        (MarketType::Derivatives, Timeframe::M30) => "Min30",
+        (MarketType::Derivatives, Timeframe::H1) => "Min60",
+        (MarketType::Derivatives, Timeframe::H4) => "Hour4",
+        (MarketType::Derivatives, Timeframe::H8) => "Hour8",
+        (MarketType::Derivatives, Timeframe::D1) => "Day1",
+        (MarketType::Derivatives, Timeframe::W1) => "Week1",
+        (MarketType::Derivatives, Timeframe::MN1) => "Month1",
+        _ => return None,
+    };
+
+    Some(interval)
 }
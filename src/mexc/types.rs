@@ -1,12 +1,65 @@
 use serde::Deserialize;
 
+use crate::{
+    errors::CandlesError,
+    series::CandleSeries,
+    types::{Instrument, Number},
+};
+
 #[derive(Deserialize)]
 pub struct MexcKlineFuturesResponse {
     pub time: Vec<i64>,
-    pub open: Vec<f64>,
+    pub open: Vec<Number>,
+
+    pub close: Vec<Number>,
+    pub low: Vec<Number>,
+    pub high: Vec<Number>,
+    #[serde(default)]
+    pub vol: Vec<Number>, // Absent from fair price klines
+    #[serde(default)]
+    pub amount: Vec<Number>, // Turnover in the quote currency
+}
+
+impl MexcKlineFuturesResponse {
+    /// Moves the columns into a series as they are, after checking they line up.
+    pub fn into_series(self, instrument: Instrument) -> Result<CandleSeries, CandlesError> {
+        let len = self.time.len();
+        let volume = match self.vol.is_empty() {
+            true => vec![Number::default(); len],
+            false => self.vol,
+        };
+
+        for (field, column) in [
+            ("open price", &self.open),
+            ("high price", &self.high),
+            ("low price", &self.low),
+            ("close price", &self.close),
+            ("volume", &volume),
+        ] {
+            if column.len() != len {
+                return Err(CandlesError::parse(field, column.len().min(len), format!("{} values for {len} timestamps", column.len())));
+            }
+        }
+
+        let quote_volume = match self.amount.len() == len {
+            true => self.amount.into_iter().map(Some).collect(),
+            false => vec![None; len],
+        };
 
-    pub close: Vec<f64>,
-    pub low: Vec<f64>,
-    pub high: Vec<f64>,
-    pub vol: Vec<f64>,
+        Ok(CandleSeries {
+            instrument,
+            // Contract klines report open times in seconds
+            timestamps: self.time.into_iter().map(|timestamp| timestamp * 1000).collect(),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume,
+            quote_volume,
+            trades: vec![None; len],
+            taker_buy_volume: vec![None; len],
+            taker_buy_quote_volume: vec![None; len],
+            is_closed: vec![false; len],
+        })
+    }
 }
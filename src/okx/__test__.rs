@@ -1,8 +1,165 @@
 #[cfg(test)]
 mod test {
+    use wiremock::{
+        Mock,
+        matchers::{path, query_param},
+    };
 
     use crate::{
-        base::BaseConnection,
+        base::ExchangeClient,
+        connections::Connection,
+        errors::CandlesError,
+        okx::main::OKX,
+        testing::{EXPECTED_CANDLES, EXPECTED_PRICE_CANDLES, assert_candles, assert_quote_volumes, config, instrument, serve, serve_status},
+        types::{CandlesRequest, MarketType, PriceSource, Timeframe},
+    };
+
+    #[tokio::test]
+    async fn test_spot_candles() {
+        let mock = Mock::given(path("/api/v5/market/candles"))
+            .and(query_param("instId", "BTC-USDT"))
+            .and(query_param("bar", "1H"));
+        let server = serve(mock, "okx/candles.json").await;
+
+        let candles = OKX::new(config(&server))
+            .get_candles(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+        assert_quote_volumes(&candles);
+        assert_eq!(candles.iter().map(|c| c.is_closed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_utc_aligned_bars() {
+        let mock = Mock::given(path("/api/v5/market/candles")).and(query_param("bar", "6Hutc"));
+        let server = serve(mock, "okx/candles_6h.json").await;
+
+        let mut instrument = instrument(Connection::OKX, "BTC-USDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::H6;
+
+        let candles = OKX::new(config(&server)).get_candles(instrument.into()).await.unwrap();
+
+        assert_eq!(candles.len(), 3);
+        assert!(candles.iter().all(|c| c.timestamp == Timeframe::H6.floor(c.timestamp)));
+    }
+
+    #[tokio::test]
+    async fn test_range_parameters() {
+        let mock = Mock::given(path("/api/v5/market/history-candles"))
+            .and(query_param("after", "1710007200001"))
+            .and(query_param("before", "1709999999999"));
+        let server = serve(mock, "okx/candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot))
+            .with_start(1710000000000)
+            .with_end(1710007200000);
+
+        assert_candles(&OKX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_http_error() {
+        let server = serve_status(503, "<html>Service Unavailable</html>").await;
+
+        let result = OKX::new(config(&server))
+            .get_candles(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::HttpStatus { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_symbol_not_found() {
+        let server = serve_status(200, r#"{"code":"51001","msg":"Instrument ID does not exist","data":[]}"#).await;
+
+        let result = OKX::new(config(&server))
+            .get_candles(instrument(Connection::OKX, "NOPE-USDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::SymbolNotFound { symbol, .. }) if symbol == "NOPE-USDT"));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_error_with_failed_status() {
+        let server = serve_status(400, r#"{"code":"51000","msg":"Parameter bar error","data":[]}"#).await;
+
+        let result = OKX::new(config(&server))
+            .get_candles(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot).into())
+            .await;
+
+        assert!(matches!(result, Err(CandlesError::ExchangeError { code, message }) if code == "51000" && message == "Parameter bar error"));
+    }
+
+    #[tokio::test]
+    async fn test_limit() {
+        let mock = Mock::given(path("/api/v5/market/candles")).and(query_param("limit", "50"));
+        let server = serve(mock, "okx/candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot)).with_limit(50);
+
+        assert_candles(&OKX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_mark_price_candles() {
+        let mock = Mock::given(path("/api/v5/market/mark-price-candles"))
+            .and(query_param("instId", "BTC-USDT-SWAP"))
+            // Mark price pages hold 100 candles whether ranged or not
+            .and(query_param("limit", "100"));
+        let server = serve(mock, "okx/mark_price_candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT-SWAP", MarketType::Derivatives)).with_price_source(PriceSource::Mark);
+        let candles = OKX::new(config(&server)).get_candles(request).await.unwrap();
+
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert_eq!(candles.iter().map(|c| c.is_closed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_ranged_mark_price_candles() {
+        let mock = Mock::given(path("/api/v5/market/history-mark-price-candles")).and(query_param("after", "1710007200001"));
+        let server = serve(mock, "okx/mark_price_candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT-SWAP", MarketType::Derivatives))
+            .with_end(1710007200000)
+            .with_price_source(PriceSource::Mark);
+
+        assert_candles(&OKX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_PRICE_CANDLES);
+    }
+
+    #[tokio::test]
+    async fn test_index_candles_by_pair() {
+        let mock = Mock::given(path("/api/v5/market/index-candles")).and(query_param("instId", "BTC-USDT"));
+        let server = serve(mock, "okx/mark_price_candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT-SWAP", MarketType::Derivatives)).with_price_source(PriceSource::Index);
+        let candles = OKX::new(config(&server)).get_candles(request).await.unwrap();
+
+        assert_candles(&candles, &EXPECTED_PRICE_CANDLES);
+        assert_eq!(candles.iter().map(|c| c.is_closed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_closed_only_drops_forming_candle() {
+        // One extra candle is requested to make up for the unconfirmed one
+        let mock = Mock::given(path("/api/v5/market/candles")).and(query_param("limit", "3"));
+        let server = serve(mock, "okx/candles.json").await;
+
+        let request = CandlesRequest::new(instrument(Connection::OKX, "BTC-USDT", MarketType::Spot))
+            .with_limit(2)
+            .with_closed_only(true);
+
+        assert_candles(&OKX::new(config(&server)).get_candles(request).await.unwrap(), &EXPECTED_CANDLES[..2]);
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live {
+
+    use crate::{
+        base::ExchangeClient,
         connections::Connection,
         okx::main::OKX,
         types::{Instrument, MarketType, Timeframe},
@@ -19,7 +176,7 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match OKX::get_candles(instrument).await {
+        match OKX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -35,7 +192,23 @@ mod test {
             timeframe: Timeframe::H1,
         };
 
-        match OKX::get_candles(instrument).await {
+        match OKX::default().get_candles(instrument.into()).await {
+            Ok(result) => examine_candles(&result),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inverse_candles() {
+        let instrument = Instrument {
+            asset_id: "bitcoin".to_owned(),
+            pair: "BTC-USD-SWAP".to_owned(),
+            connection: Connection::OKX,
+            market_type: MarketType::Inverse,
+            timeframe: Timeframe::H1,
+        };
+
+        match OKX::default().get_candles(instrument.into()).await {
             Ok(result) => examine_candles(&result),
             Err(err) => panic!("{}", err),
         }
@@ -1,75 +1,184 @@
+use std::{sync::LazyLock, time::Duration};
+
 use async_trait::async_trait;
 use serde_json::Value;
+use strum::IntoEnumIterator;
 
 use crate::{
-    base::BaseConnection,
+    backfill::PageDirection,
+    base::ExchangeClient,
+    capabilities::{Capabilities, ExtendedField},
+    config::ClientConfig,
+    connections::Connection,
     errors::CandlesError,
-    types::{Candle, Timeframe},
-    utils::{DataWrapper, parse_string_to_f64},
+    rate_limit::{RateLimit, RateLimiter},
+    types::{Candle, CandlesRequest, MarketType, PriceSource, Timeframe},
+    utils::{DataWrapperWithMsgCode, candle_array, map_symbol_not_found, parse_number, parse_optional_number, parse_timestamp, price_candle},
 };
 
-pub struct OKX;
+pub const BASE_URL: &str = "https://www.okx.com";
+
+const INSTRUMENT_NOT_FOUND_CODE: &str = "51001";
+const CANDLES_MAX_LIMIT: usize = 300;
+const HISTORY_CANDLES_MAX_LIMIT: usize = 100;
+
+// Documented per-IP limits: 40 requests per 2 seconds for candles and mark-price-candles, 20 for history-candles and
+// index-candles, 10 for history-mark-price-candles and history-index-candles
+static CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(40, Duration::from_secs(2))));
+static HISTORY_CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(20, Duration::from_secs(2))));
+static MARK_CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(40, Duration::from_secs(2))));
+static HISTORY_MARK_CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(10, Duration::from_secs(2))));
+static INDEX_CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(20, Duration::from_secs(2))));
+static HISTORY_INDEX_CANDLES_LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(10, Duration::from_secs(2))));
+
+#[derive(Debug, Clone, Default)]
+pub struct OKX {
+    config: ClientConfig,
+}
+
+impl OKX {
+    pub fn new(config: ClientConfig) -> Self {
+        Self { config }
+    }
+}
 
 #[async_trait]
-impl BaseConnection for OKX {
-    async fn get_candles(instrument: crate::types::Instrument) -> Result<Vec<crate::types::Candle>, crate::errors::CandlesError> {
-        let okx_timeframe = match instrument.timeframe {
-            Timeframe::M3 => "3m",
-            Timeframe::M5 => "5m",
-            Timeframe::M15 => "15m",
-            Timeframe::M30 => "30m",
-            Timeframe::H1 => "1H",
-            Timeframe::H4 => "4H",
-            Timeframe::D1 => "1D",
-            Timeframe::W1 => "1W",
-            Timeframe::MN1 => "1M",
+impl ExchangeClient for OKX {
+    async fn get_page(&self, request: CandlesRequest) -> Result<Vec<Candle>, CandlesError> {
+        let instrument = &request.instrument;
+
+        let okx_timeframe = interval(&instrument.timeframe, &instrument.market_type).ok_or_else(|| CandlesError::UnsupportedTimeframe {
+            connection: Connection::OKX,
+            timeframe: instrument.timeframe.clone(),
+        })?;
+
+        let limit = request.limit.unwrap_or(usize::MAX).min(self.max_page_size(&request));
+
+        // The candles endpoints only serve the most recent 1440 bars, so ranged queries go to their history counterparts
+        let (endpoint, limiter) = match (request.price_source, request.has_range()) {
+            (PriceSource::Last, false) => ("candles", &*CANDLES_LIMITER),
+            (PriceSource::Last, true) => ("history-candles", &*HISTORY_CANDLES_LIMITER),
+            (PriceSource::Index, false) => ("index-candles", &*INDEX_CANDLES_LIMITER),
+            (PriceSource::Index, true) => ("history-index-candles", &*HISTORY_INDEX_CANDLES_LIMITER),
+            _ if instrument.market_type == MarketType::Spot => return Err(CandlesError::unsupported_price_source(Connection::OKX, &request)),
+            (PriceSource::Mark, false) => ("mark-price-candles", &*MARK_CANDLES_LIMITER),
+            (PriceSource::Mark, true) => ("history-mark-price-candles", &*HISTORY_MARK_CANDLES_LIMITER),
+            _ => return Err(CandlesError::unsupported_price_source(Connection::OKX, &request)),
         };
 
-        let url = format!("https://www.okx.com/api/v5/market/candles?instId={}&bar={}&limit=300", instrument.pair, okx_timeframe);
+        // Indices are named by their pair, which every spot, swap, future and option id starts with: BTC-USDT-SWAP follows BTC-USDT
+        let inst_id = match request.price_source {
+            PriceSource::Index => instrument.pair.splitn(3, '-').take(2).collect::<Vec<_>>().join("-"),
+            _ => instrument.pair.clone(),
+        };
 
-        let response = reqwest::get(&url)
-            .await
-            .map_err(|e| CandlesError::Other(format!("Failed to fetch candles from OKX: {e}")))?;
+        let mut url = format!(
+            "{}/api/v5/market/{}?instId={}&bar={}&limit={}",
+            self.config.base_url(&instrument.market_type, BASE_URL),
+            endpoint,
+            inst_id,
+            okx_timeframe,
+            limit
+        );
 
-        if !response.status().is_success() {
-            return Err(CandlesError::Other(format!(
-                "OKX API request failed with status {}: {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            )));
+        // OKX bounds are exclusive: `after` returns older records, `before` returns newer ones
+        if let Some(end) = request.end {
+            url.push_str(&format!("&after={}", end + 1));
+        }
+        if let Some(start) = request.start {
+            url.push_str(&format!("&before={}", start - 1));
         }
 
-        let response_body: DataWrapper<Vec<Value>> = response.json().await.map_err(|e| CandlesError::Other(format!("Failed to parse OKX JSON response: {e}")))?;
+        let response: DataWrapperWithMsgCode<String, Vec<Value>> = self.config.get_envelope(&url, limiter, 1).await?;
+        let data = response
+            .into_data("0")
+            .map_err(|e| map_symbol_not_found(e, Connection::OKX, &instrument.pair, |code, _| code == INSTRUMENT_NOT_FOUND_CODE))?;
 
-        let mut candles = Vec::with_capacity(response_body.data.len());
+        let mut candles = Vec::with_capacity(data.len());
 
-        for (index, value) in response_body.data.iter().enumerate().rev() {
-            let candle_array = value
-                .as_array()
-                .ok_or_else(|| CandlesError::Other(format!("Expected array for candle data at index {index}")))?;
+        for (index, value) in data.iter().enumerate().rev() {
+            // Mark and index price rows are [ts, o, h, l, c, confirm]
+            if request.price_source != PriceSource::Last {
+                let confirm = value.as_array().and_then(|row| row.get(5)).and_then(Value::as_str);
 
-            if candle_array.len() < 6 {
-                return Err(CandlesError::Other(format!(
-                    "Insufficient data in candle array at index {}: expected at least 6 elements, got {}",
-                    index,
-                    candle_array.len()
-                )));
+                candles.push(Candle {
+                    is_closed: confirm == Some("1"),
+                    ..price_candle(value, index)?
+                });
+                continue;
             }
 
+            let candle_array = candle_array(value, index, 6)?;
+
             candles.push(Candle {
-                timestamp: candle_array[0]
-                    .as_str()
-                    .ok_or_else(|| CandlesError::Other(format!("Invalid timestamp at index {} with value {}", index, candle_array[0])))?
-                    .parse::<i64>()
-                    .map_err(|_| CandlesError::Other(format!("Failed to parse timestamp at index {} with value {}", index, candle_array[0])))?,
-                open: parse_string_to_f64(&candle_array[1], "open price", index)?,
-                high: parse_string_to_f64(&candle_array[2], "high price", index)?,
-                low: parse_string_to_f64(&candle_array[3], "low price", index)?,
-                close: parse_string_to_f64(&candle_array[4], "close price", index)?,
-                volume: parse_string_to_f64(&candle_array[5], "volume", index)?,
+                timestamp: parse_timestamp(&candle_array[0], index)?,
+                open: parse_number(&candle_array[1], "open price", index)?,
+                high: parse_number(&candle_array[2], "high price", index)?,
+                low: parse_number(&candle_array[3], "low price", index)?,
+                close: parse_number(&candle_array[4], "close price", index)?,
+                volume: parse_number(&candle_array[5], "volume", index)?,
+                // volCcyQuote, in the quote currency for spot and swaps alike
+                quote_volume: parse_optional_number(candle_array.get(7), "quote volume", index)?,
+                is_closed: candle_array.get(8).is_some_and(|confirm| *confirm == "1"),
+                ..Default::default()
             });
         }
 
         Ok(candles)
     }
+
+    fn max_page_size(&self, request: &CandlesRequest) -> usize {
+        match (request.price_source, request.has_range()) {
+            (PriceSource::Last, false) => CANDLES_MAX_LIMIT,
+            // History pages, mark and index price candles hold 100
+            _ => HISTORY_CANDLES_MAX_LIMIT,
+        }
+    }
+
+    fn page_direction(&self, _market_type: &MarketType) -> PageDirection {
+        PageDirection::Backward
+    }
+
+    fn capabilities(&self, market_type: &MarketType) -> Option<Capabilities> {
+        Some(Capabilities {
+            market_type: market_type.clone(),
+            timeframes: Timeframe::iter().filter(|timeframe| interval(timeframe, market_type).is_some()).collect(),
+            // Ranged requests go to the history endpoint
+            max_page_size: HISTORY_CANDLES_MAX_LIMIT,
+            max_history: None,
+            price_source_max_history: None,
+            extended_fields: vec![ExtendedField::QuoteVolume],
+            price_sources: match market_type {
+                MarketType::Spot => vec![PriceSource::Last, PriceSource::Index],
+                _ => vec![PriceSource::Last, PriceSource::Mark, PriceSource::Index],
+            },
+            page_direction: self.page_direction(market_type),
+            market_in_symbol: true,
+        })
+    }
+}
+
+/// The OKX interval for `timeframe`, `None` when it is not served.
+fn interval(timeframe: &Timeframe, _market_type: &MarketType) -> Option<&'static str> {
+    let interval = match timeframe {
+        Timeframe::S1 => "1s",
+        Timeframe::M1 => "1m",
+        Timeframe::M3 => "3m",
+        Timeframe::M5 => "5m",
+        Timeframe::M15 => "15m",
+        Timeframe::M30 => "30m",
+        Timeframe::H1 => "1H",
+        Timeframe::H2 => "2H",
+        Timeframe::H4 => "4H",
+        // Plain 6H and longer bars open on Hong Kong time, the `utc` variants on the boundaries `Timeframe::floor` expects
+        Timeframe::H6 => "6Hutc",
+        Timeframe::H12 => "12Hutc",
+        Timeframe::D1 => "1Dutc",
+        Timeframe::D3 => "3Dutc",
+        Timeframe::W1 => "1Wutc",
+        Timeframe::MN1 => "1Mutc",
+        Timeframe::H8 => return None,
+    };
+
+    Some(interval)
 }
@@ -0,0 +1,162 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::{
+    connections::Connection,
+    delivery::{SETTLEMENT_HOUR, ddmonyy, parse_ddmonyy, yymmdd},
+    errors::CandlesError,
+    types::{Instrument, MarketType, Number, Timeframe},
+};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display, EnumString, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+/// An option contract, the parts each exchange builds its symbol from.
+///
+/// `quote` is the currency the option is settled in: `USD` for OKX's coin-settled options, `USDT` on Binance and `USDC`
+/// on Bybit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionContract {
+    pub base: String,
+    pub quote: String,
+    pub expiry: NaiveDate,
+    pub strike: Number,
+    pub kind: OptionKind,
+}
+
+impl OptionContract {
+    pub fn new(base: impl Into<String>, quote: impl Into<String>, expiry: NaiveDate, strike: Number, kind: OptionKind) -> Self {
+        Self {
+            base: base.into().to_uppercase(),
+            quote: quote.into().to_uppercase(),
+            expiry,
+            strike,
+            kind,
+        }
+    }
+
+    /// When the option expires and its last candle closes.
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expiry.and_hms_opt(SETTLEMENT_HOUR, 0, 0).unwrap_or_default().and_utc()
+    }
+
+    /// The option's symbol on `connection`, e.g. `BTC-250328-100000-C` on Binance or `BTC-USD-250328-100000-C` on OKX.
+    pub fn symbol(&self, connection: &Connection) -> Result<String, CandlesError> {
+        let (base, quote, strike) = (&self.base, &self.quote, &self.strike);
+        let kind = match self.kind {
+            OptionKind::Call => "C",
+            OptionKind::Put => "P",
+        };
+
+        let symbol = match connection {
+            Connection::Binance => format!("{base}-{}-{strike}-{kind}", self.expiry.format("%y%m%d")),
+            Connection::OKX => format!("{base}-{quote}-{}-{strike}-{kind}", self.expiry.format("%y%m%d")),
+            Connection::Bybit => format!("{base}-{}-{strike}-{kind}", ddmonyy(&self.expiry)),
+            Connection::BloFin | Connection::BingX | Connection::HTX | Connection::Mexc => {
+                return Err(CandlesError::UnsupportedMarketType {
+                    connection: connection.clone(),
+                    market_type: MarketType::Options,
+                });
+            }
+        };
+
+        Ok(symbol)
+    }
+
+    /// Reads an option symbol of `connection` back into its parts, `None` when `symbol` is not an option.
+    pub fn parse(connection: &Connection, symbol: &str) -> Option<Self> {
+        let symbol = symbol.to_uppercase();
+        let parts: Vec<&str> = symbol.split('-').collect();
+
+        let (base, quote, expiry, strike, kind) = match (connection, parts.as_slice()) {
+            (Connection::Binance, [base, expiry, strike, kind]) => (*base, "USDT", yymmdd(expiry)?, *strike, *kind),
+            (Connection::OKX, [base, quote, expiry, strike, kind]) => (*base, *quote, yymmdd(expiry)?, *strike, *kind),
+            // Newer Bybit symbols name the settlement currency, older ones are settled in USDC
+            (Connection::Bybit, [base, expiry, strike, kind]) => (*base, "USDC", parse_ddmonyy(expiry)?, *strike, *kind),
+            (Connection::Bybit, [base, expiry, strike, kind, quote]) => (*base, *quote, parse_ddmonyy(expiry)?, *strike, *kind),
+            _ => return None,
+        };
+
+        let kind = match kind {
+            "C" => OptionKind::Call,
+            "P" => OptionKind::Put,
+            _ => return None,
+        };
+
+        match base.is_empty() {
+            true => None,
+            false => Some(Self::new(base, quote, expiry, strike.parse().ok()?, kind)),
+        }
+    }
+
+    /// An [`MarketType::Options`] instrument for this contract on `connection`.
+    pub fn instrument(&self, asset_id: impl Into<String>, connection: Connection, timeframe: Timeframe) -> Result<Instrument, CandlesError> {
+        Ok(Instrument {
+            asset_id: asset_id.into(),
+            pair: self.symbol(&connection)?,
+            connection,
+            market_type: MarketType::Options,
+            timeframe,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::{
+        connections::Connection,
+        errors::CandlesError,
+        options::{OptionContract, OptionKind},
+        types::{MarketType, Timeframe},
+    };
+
+    fn contract(quote: &str) -> OptionContract {
+        let expiry = NaiveDate::from_ymd_opt(2025, 3, 28).unwrap();
+
+        OptionContract::new("btc", quote, expiry, "100000".parse().unwrap(), OptionKind::Call)
+    }
+
+    #[test]
+    fn test_symbols_round_trip() {
+        let cases = [
+            (Connection::Binance, "USDT", "BTC-250328-100000-C"),
+            (Connection::OKX, "USD", "BTC-USD-250328-100000-C"),
+            (Connection::Bybit, "USDC", "BTC-28MAR25-100000-C"),
+        ];
+
+        for (connection, quote, symbol) in cases {
+            assert_eq!(contract(quote).symbol(&connection).unwrap(), symbol);
+            assert_eq!(OptionContract::parse(&connection, symbol), Some(contract(quote)), "{connection} {symbol}");
+        }
+
+        let put = OptionContract::parse(&Connection::Bybit, "ETH-27JUN25-2500.5-P-USDT").unwrap();
+        assert_eq!((put.quote.as_str(), put.kind, put.strike.to_string()), ("USDT", OptionKind::Put, "2500.5".to_string()));
+    }
+
+    #[test]
+    fn test_non_option_symbols() {
+        assert_eq!(OptionContract::parse(&Connection::OKX, "BTC-USD-250328"), None);
+        assert_eq!(OptionContract::parse(&Connection::Binance, "BTCUSDT_250328"), None);
+        assert_eq!(OptionContract::parse(&Connection::Binance, "BTC-250328-100000-X"), None);
+    }
+
+    #[test]
+    fn test_instrument() {
+        let instrument = contract("USDT").instrument("bitcoin", Connection::Binance, Timeframe::H1).unwrap();
+
+        assert_eq!(instrument.pair, "BTC-250328-100000-C");
+        assert_eq!(instrument.market_type, MarketType::Options);
+        assert_eq!(instrument.option_contract(), Some(contract("USDT")));
+
+        let result = contract("USDT").instrument("bitcoin", Connection::HTX, Timeframe::H1);
+        assert!(matches!(result, Err(CandlesError::UnsupportedMarketType { connection: Connection::HTX, .. })));
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use reqwest::{Url, header::HeaderMap};
+use tokio::time::{Instant, sleep_until};
+
+/// Limiters in use, by the budget they copy and the host they count, see [`RateLimiter::for_host`]
+type HostLimiters = HashMap<(usize, String), Arc<RateLimiter>>;
+
+static HOST_LIMITERS: LazyLock<Mutex<HostLimiters>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A request budget: `capacity` weight units per `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(capacity: u32, interval: Duration) -> Self {
+        Self { capacity, interval }
+    }
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    used: u32,
+    paused_until: Option<Instant>,
+}
+
+/// Fixed-window limiter shared by every client of one exchange host.
+///
+/// Callers queue in arrival order until their request weight fits in the current window, instead of being rejected.
+/// The exchange's own accounting (usage headers, `Retry-After`) tightens the local estimate when it is behind.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    used_weight_header: Option<&'static str>,
+    queue: tokio::sync::Mutex<()>,
+    window: Mutex<Window>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            used_weight_header: None,
+            queue: tokio::sync::Mutex::new(()),
+            window: Mutex::new(Window {
+                start: Instant::now(),
+                used: 0,
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Response header reporting the weight already used in the current window, e.g. Binance's `X-MBX-USED-WEIGHT-1M`
+    pub fn with_used_weight_header(mut self, header: &'static str) -> Self {
+        self.used_weight_header = Some(header);
+        self
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// The limiter counting this one's budget for `url`'s host, shared by every client of that host.
+    ///
+    /// Hosts are counted apart so that a client pointed at a testnet or mock server through
+    /// [`crate::config::ClientConfig::base_url`] does not spend the production budget.
+    pub(crate) fn for_host(&'static self, url: &str) -> Arc<RateLimiter> {
+        let host = Url::parse(url).map_or_else(|_| url.to_string(), |url| url.origin().ascii_serialization());
+        let mut limiters = HOST_LIMITERS.lock().unwrap();
+
+        limiters
+            .entry((self as *const Self as usize, host))
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    used_weight_header: self.used_weight_header,
+                    ..Self::new(self.limit)
+                })
+            })
+            .clone()
+    }
+
+    /// Waits until `weight` fits in the budget and reserves it.
+    pub async fn acquire(&self, weight: u32) {
+        // Holding the queue lock while waiting keeps callers in arrival order
+        let _turn = self.queue.lock().await;
+
+        loop {
+            let wait_until = {
+                let mut window = self.window.lock().unwrap();
+                let now = Instant::now();
+
+                match window.paused_until {
+                    Some(until) if until > now => until,
+                    _ => {
+                        window.paused_until = None;
+
+                        if now >= window.start + self.limit.interval {
+                            window.start = now;
+                            window.used = 0;
+                        }
+
+                        // A request heavier than the whole budget still goes through on a fresh window
+                        if window.used + weight <= self.limit.capacity || window.used == 0 {
+                            window.used += weight;
+                            return;
+                        }
+
+                        window.start + self.limit.interval
+                    }
+                }
+            };
+
+            sleep_until(wait_until).await;
+        }
+    }
+
+    /// Adopts the exchange's reported usage when it is higher than the local count.
+    pub fn observe(&self, headers: &HeaderMap) {
+        let Some(used) = self
+            .used_weight_header
+            .and_then(|header| headers.get(header))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u32>().ok())
+        else {
+            return;
+        };
+
+        let mut window = self.window.lock().unwrap();
+        window.used = window.used.max(used);
+    }
+
+    /// Stops handing out budget for `duration`, e.g. after a 429 with `Retry-After`.
+    pub fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut window = self.window.lock().unwrap();
+        window.paused_until = Some(window.paused_until.map_or(until, |current| current.max(until)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, LazyLock},
+        time::Duration,
+    };
+
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use tokio::time::Instant;
+
+    use crate::rate_limit::{RateLimit, RateLimiter};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queues_until_next_window() {
+        let limiter = RateLimiter::new(RateLimit::new(10, MINUTE));
+        let started = Instant::now();
+
+        limiter.acquire(5).await;
+        limiter.acquire(5).await;
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        limiter.acquire(5).await;
+        assert_eq!(started.elapsed(), MINUTE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adopts_reported_usage() {
+        let limiter = RateLimiter::new(RateLimit::new(10, MINUTE)).with_used_weight_header("x-mbx-used-weight-1m");
+        let started = Instant::now();
+
+        limiter.acquire(1).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-mbx-used-weight-1m", HeaderValue::from_static("10"));
+        limiter.observe(&headers);
+
+        limiter.acquire(1).await;
+        assert_eq!(started.elapsed(), MINUTE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause() {
+        let limiter = RateLimiter::new(RateLimit::new(10, MINUTE));
+        let started = Instant::now();
+
+        limiter.pause(Duration::from_secs(5));
+        limiter.acquire(1).await;
+
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hosts_have_their_own_budget() {
+        static LIMITER: LazyLock<RateLimiter> = LazyLock::new(|| RateLimiter::new(RateLimit::new(10, MINUTE)));
+        let started = Instant::now();
+
+        let production = LIMITER.for_host("https://api.binance.com/api/v3/klines?symbol=BTCUSDT");
+        let testnet = LIMITER.for_host("https://testnet.binance.vision/api/v3/klines?symbol=BTCUSDT");
+
+        assert!(Arc::ptr_eq(&production, &LIMITER.for_host("https://api.binance.com/api/v3/uiKlines")));
+        assert!(!Arc::ptr_eq(&production, &testnet));
+
+        production.acquire(10).await;
+        testnet.acquire(10).await;
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,283 @@
+use std::future::Future;
+
+use chrono::Utc;
+use strum::IntoEnumIterator;
+
+use crate::{
+    errors::CandlesError,
+    types::{Candle, CandlesRequest, Timeframe},
+    utils::mark_closed,
+};
+
+/// Runs `fetch`, and when the exchange has no such timeframe builds it from the coarsest finer one it serves.
+///
+/// `fetch` is a full candles query, normally `get_candles` or `backfill` without resampling. Source timeframes must divide
+/// the requested one evenly, so 3m comes from 1m, 2h from 1h and 1M from 1d.
+pub async fn resampled<F, Fut>(request: CandlesRequest, fetch: F) -> Result<Vec<Candle>, CandlesError>
+where
+    F: Fn(CandlesRequest) -> Fut,
+    Fut: Future<Output = Result<Vec<Candle>, CandlesError>>,
+{
+    let unsupported = match fetch(request.clone()).await {
+        Err(error @ CandlesError::UnsupportedTimeframe { .. }) => error,
+        result => return result,
+    };
+
+    for source in sources(&request.instrument.timeframe) {
+        match fetch(source_request(&request, &source)).await {
+            Err(CandlesError::UnsupportedTimeframe { .. }) => continue,
+            result => return Ok(finish(result?, &request, &source)),
+        }
+    }
+
+    Err(unsupported)
+}
+
+/// Rolls ascending candles up into `timeframe` buckets: first open, highest high, lowest low, last close and summed volumes.
+///
+/// Extended fields are summed only when every source candle in the bucket has them. The result is left open
+/// (`is_closed` false) for the caller to decide.
+pub fn resample(candles: &[Candle], timeframe: &Timeframe) -> Vec<Candle> {
+    let mut buckets: Vec<Candle> = Vec::new();
+
+    for candle in candles {
+        let open = timeframe.floor(candle.timestamp);
+
+        match buckets.last_mut() {
+            Some(bucket) if bucket.timestamp == open => {
+                if candle.high > bucket.high {
+                    bucket.high = candle.high;
+                }
+                if candle.low < bucket.low {
+                    bucket.low = candle.low;
+                }
+                bucket.close = candle.close;
+                bucket.volume += candle.volume;
+                bucket.quote_volume = bucket.quote_volume.zip(candle.quote_volume).map(|(a, b)| a + b);
+                bucket.trades = bucket.trades.zip(candle.trades).map(|(a, b)| a + b);
+                bucket.taker_buy_volume = bucket.taker_buy_volume.zip(candle.taker_buy_volume).map(|(a, b)| a + b);
+                bucket.taker_buy_quote_volume = bucket.taker_buy_quote_volume.zip(candle.taker_buy_quote_volume).map(|(a, b)| a + b);
+            }
+            _ => buckets.push(Candle {
+                timestamp: open,
+                is_closed: false,
+                ..candle.clone()
+            }),
+        }
+    }
+
+    buckets
+}
+
+/// Finer timeframes that tile `timeframe` exactly, coarsest first so the fewest candles are fetched.
+pub(crate) fn sources(timeframe: &Timeframe) -> Vec<Timeframe> {
+    let tiles = |seconds: i64| match seconds_of(timeframe) {
+        Some(target) => seconds < target && target % seconds == 0,
+        // Months are tiled by days and anything dividing a day
+        None => (24 * 60 * 60) % seconds == 0,
+    };
+
+    let mut sources: Vec<_> = Timeframe::iter().filter(|source| seconds_of(source).is_some_and(tiles)).collect();
+    sources.sort_by_key(|source| std::cmp::Reverse(seconds_of(source)));
+
+    sources
+}
+
+fn seconds_of(timeframe: &Timeframe) -> Option<i64> {
+    timeframe.duration().map(|duration| duration.num_seconds())
+}
+
+/// `request` widened to whole target candles and expressed in `source` candles.
+fn source_request(request: &CandlesRequest, source: &Timeframe) -> CandlesRequest {
+    let target = &request.instrument.timeframe;
+    // A month holds at most 31 days
+    let ratio = seconds_of(target).unwrap_or(31 * 24 * 60 * 60) / seconds_of(source).unwrap_or(1);
+
+    let mut source_request = request.clone();
+    source_request.instrument.timeframe = source.clone();
+    source_request.start = request.start.map(|start| target.floor(start));
+    source_request.end = request.end.map(|end| target.next_close(end) - 1);
+    // One spare target candle covers a partial first bucket
+    source_request.limit = request.limit.map(|limit| (limit + 1) * ratio as usize);
+    // Whether a target candle is closed is decided after resampling
+    source_request.closed_only = false;
+
+    source_request
+}
+
+/// Resamples the source candles and applies the original request's range, limit and `closed_only`.
+///
+/// Pages are cut by count as often as by range, so either end may hold only part of a target candle. Those partial
+/// candles are dropped, except for the latest one when it is still forming.
+fn finish(source: Vec<Candle>, request: &CandlesRequest, source_timeframe: &Timeframe) -> Vec<Candle> {
+    let timeframe = &request.instrument.timeframe;
+    let mut candles = resample(&source, timeframe);
+
+    if source.first().is_some_and(|first| timeframe.floor(first.timestamp) != first.timestamp) {
+        candles.remove(0);
+    }
+
+    let now = Utc::now().timestamp_millis();
+    if let (Some(last), Some(bucket)) = (source.last(), candles.last()) {
+        let bucket_close = timeframe.close_time(bucket.timestamp);
+
+        if source_timeframe.close_time(last.timestamp) != bucket_close && bucket_close <= now {
+            candles.pop();
+        }
+    }
+
+    mark_closed(&mut candles, timeframe);
+
+    candles.retain(|c| request.contains(c.timestamp) && (c.is_closed || !request.closed_only));
+
+    if let Some(limit) = request.limit {
+        candles.drain(..candles.len().saturating_sub(limit));
+    }
+
+    candles
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        connections::Connection,
+        errors::CandlesError,
+        resample::{resample, resampled, sources},
+        testing::{float, instrument},
+        types::{Candle, CandlesRequest, MarketType, Timeframe},
+    };
+
+    const MINUTE: i64 = 60 * 1000;
+    const DAY: i64 = 24 * MINUTE * 60;
+
+    fn candle(timestamp: i64, price: i64) -> Candle {
+        let price = price.to_string().parse().unwrap();
+
+        Candle {
+            timestamp,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: "1".parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sources_prefer_coarsest() {
+        assert_eq!(sources(&Timeframe::M3), [Timeframe::M1, Timeframe::S1]);
+        assert_eq!(sources(&Timeframe::H6)[0], Timeframe::H2);
+        assert_eq!(sources(&Timeframe::D3)[0], Timeframe::D1);
+        assert_eq!(sources(&Timeframe::MN1)[0], Timeframe::D1);
+        assert!(sources(&Timeframe::S1).is_empty());
+    }
+
+    #[test]
+    fn test_weeks_and_months_align_to_calendar() {
+        // Sunday 2024-03-03 to Tuesday 2024-03-05, across a Monday
+        let sunday = Utc.with_ymd_and_hms(2024, 3, 3, 0, 0, 0).unwrap().timestamp_millis();
+        let candles: Vec<_> = (0..3).map(|day| candle(sunday + day * DAY, 10 + day)).collect();
+
+        let weeks = resample(&candles, &Timeframe::W1);
+        assert_eq!(weeks.iter().map(|c| c.timestamp).collect::<Vec<_>>(), [sunday - 6 * DAY, sunday + DAY]);
+        assert_eq!(float(weeks[1].open), 11.0);
+        assert_eq!(float(weeks[1].close), 12.0);
+        assert_eq!(float(weeks[1].volume), 2.0);
+
+        // Thursday 2024-02-29 to Friday 2024-03-01, across a month end
+        let thursday = Utc.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap().timestamp_millis();
+        let months = resample(&[candle(thursday, 1), candle(thursday + DAY, 2)], &Timeframe::MN1);
+        assert_eq!(
+            months.iter().map(|c| c.timestamp).collect::<Vec<_>>(),
+            [Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap().timestamp_millis(), thursday + DAY]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resampled_falls_back_to_finer_timeframe() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap().timestamp_millis();
+        let mut instrument = instrument(Connection::Mexc, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::D3;
+
+        // Serves days only, ignoring the range like a latest-page request would
+        let fetch = |request: CandlesRequest| async move {
+            match request.instrument.timeframe {
+                Timeframe::D1 => Ok((0..8).map(|day| candle(start + day * DAY, day)).collect()),
+                timeframe => Err(CandlesError::UnsupportedTimeframe {
+                    connection: Connection::Mexc,
+                    timeframe,
+                }),
+            }
+        };
+
+        let candles = resampled(CandlesRequest::new(instrument.clone()).with_limit(2), fetch).await.unwrap();
+
+        // 2024-03-01 is day 19783 since the epoch, so 3 day candles open on 02-29, 03-03 and 03-06. The first is only partly
+        // covered and dropped, the last ends with the page on 03-08
+        assert_eq!(candles.iter().map(|c| c.timestamp).collect::<Vec<_>>(), [start + 2 * DAY, start + 5 * DAY]);
+        assert_eq!((float(candles[0].open), float(candles[0].close), float(candles[0].high)), (2.0, 4.0, 4.0));
+
+        instrument.timeframe = Timeframe::S1;
+        let result = resampled(CandlesRequest::new(instrument), fetch).await;
+        assert!(matches!(result, Err(CandlesError::UnsupportedTimeframe { timeframe: Timeframe::S1, .. })));
+    }
+
+    /// A source serving `count` 1m candles from `first`, whatever the request, like a page cut by count.
+    fn minutes(first: i64, count: i64) -> impl Fn(CandlesRequest) -> std::future::Ready<Result<Vec<Candle>, CandlesError>> {
+        move |request: CandlesRequest| {
+            std::future::ready(match request.instrument.timeframe {
+                Timeframe::M1 => Ok((0..count).map(|minute| candle(first + minute * MINUTE, minute)).collect()),
+                timeframe => Err(CandlesError::UnsupportedTimeframe {
+                    connection: Connection::Mexc,
+                    timeframe,
+                }),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_last_bucket_is_dropped() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap().timestamp_millis();
+        let mut instrument = instrument(Connection::Mexc, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::M3;
+
+        // A page of 5 minutes from the start, cut by count: the 3m candle at 00:03 holds only 2 of them
+        let candles = resampled(CandlesRequest::new(instrument).with_start(start), minutes(start, 5)).await.unwrap();
+
+        assert_eq!(candles.iter().map(|c| c.timestamp).collect::<Vec<_>>(), [start]);
+        assert_eq!((float(candles[0].close), float(candles[0].volume)), (2.0, 3.0));
+        assert!(candles[0].is_closed);
+    }
+
+    #[tokio::test]
+    async fn test_partial_first_bucket_is_dropped_with_start() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap().timestamp_millis();
+        let mut instrument = instrument(Connection::OKX, "BTC-USDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::M3;
+
+        // A backward page from the end reaches back only to 00:01, mid-bucket, although the range starts at 00:00
+        let request = CandlesRequest::new(instrument).with_start(start).with_end(start + 9 * MINUTE);
+        let candles = resampled(request, minutes(start + MINUTE, 8)).await.unwrap();
+
+        assert_eq!(candles.iter().map(|c| c.timestamp).collect::<Vec<_>>(), [start + 3 * MINUTE, start + 6 * MINUTE]);
+        assert_eq!(float(candles[0].open), 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_forming_last_bucket_is_kept() {
+        let mut instrument = instrument(Connection::Mexc, "BTCUSDT", MarketType::Spot);
+        instrument.timeframe = Timeframe::H1;
+
+        // The current hour so far, up to the minute now falls in
+        let now = Utc::now().timestamp_millis();
+        let hour = Timeframe::H1.floor(now);
+        let elapsed = (now - hour) / MINUTE + 1;
+
+        let candles = resampled(CandlesRequest::new(instrument), minutes(hour, elapsed)).await.unwrap();
+
+        assert_eq!(candles.iter().map(|c| (c.timestamp, c.is_closed)).collect::<Vec<_>>(), [(hour, false)]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use crate::errors::CandlesError;
+
+/// When and how long to wait before repeating a failed request.
+///
+/// Delays grow exponentially from `base_delay`, are capped at `max_delay` and reduced by a random share of up to `jitter`
+/// so that many clients failing together do not retry in lockstep.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one, 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay, between 0 and 1, that is randomized away
+    pub jitter: f64,
+    /// HTTP statuses worth retrying; 429 also covers rate limit responses
+    pub retryable_statuses: Vec<u16>,
+    /// Retry timeouts, refused connections and resets
+    pub retry_transport_errors: bool,
+    /// Wait as long as a 429's `Retry-After` header asks, giving up when that exceeds `max_delay`
+    pub honor_retry_after: bool,
+    /// Retry a Binance 418 (IP ban) once its `Retry-After` has passed, if that fits within `max_delay`. Bans without the
+    /// header are never retried, as every request made while banned extends the ban
+    pub retry_ip_bans: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+            retry_transport_errors: true,
+            honor_retry_after: true,
+            retry_ip_bans: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait before attempt `attempt + 1` after `error`, or `None` if it should not be retried.
+    pub fn delay(&self, attempt: u32, error: &CandlesError) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        match error {
+            CandlesError::RateLimited { retry_after } if self.retryable_statuses.contains(&429) => match retry_after {
+                Some(retry_after) if self.honor_retry_after => (*retry_after <= self.max_delay).then_some(*retry_after),
+                _ => Some(self.backoff(attempt)),
+            },
+            // Retrying a banned IP only extends the ban, so it waits for the exchange's word or gives up
+            CandlesError::IpBanned { retry_after: Some(retry_after) } if self.retry_ip_bans => (*retry_after <= self.max_delay).then_some(*retry_after),
+            CandlesError::HttpStatus { status, .. } if self.retryable_statuses.contains(status) => Some(self.backoff(attempt)),
+            CandlesError::Reqwest(e) if self.retry_transport_errors && !e.is_builder() => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+
+        capped.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random_fraction())
+    }
+}
+
+/// A random number in [0, 1), good enough for spreading retries
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use wiremock::{Mock, ResponseTemplate, matchers::any};
+
+    use crate::{
+        base::ExchangeClient,
+        binance::main::Binance,
+        connections::Connection,
+        errors::CandlesError,
+        retry::RetryPolicy,
+        testing::{EXPECTED_CANDLES, assert_candles, config, instrument, serve},
+        types::MarketType,
+    };
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn http_status(status: u16) -> CandlesError {
+        CandlesError::HttpStatus { status, body: String::new() }
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy { max_attempts: 10, ..policy() };
+
+        assert_eq!(policy.delay(1, &http_status(503)), Some(Duration::from_millis(100)));
+        assert_eq!(policy.delay(2, &http_status(503)), Some(Duration::from_millis(200)));
+        assert_eq!(policy.delay(3, &http_status(503)), Some(Duration::from_millis(400)));
+        assert_eq!(policy.delay(6, &http_status(503)), Some(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy { jitter: 0.5, ..policy() };
+
+        for _ in 0..100 {
+            let delay = policy.delay(1, &http_status(503)).unwrap();
+            assert!(delay > Duration::from_millis(50) && delay <= Duration::from_millis(100), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_non_retryable_errors() {
+        let policy = policy();
+
+        assert_eq!(policy.delay(1, &http_status(400)), None);
+        assert_eq!(policy.delay(3, &http_status(503)), None);
+        assert_eq!(policy.delay(1, &CandlesError::parse("open price", 0, "x")), None);
+        assert_eq!(RetryPolicy::disabled().delay(1, &http_status(503)), None);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let policy = policy();
+        let rate_limited = |secs| CandlesError::RateLimited {
+            retry_after: Some(Duration::from_secs(secs)),
+        };
+
+        assert_eq!(policy.delay(1, &rate_limited(1)), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(1, &rate_limited(60)), None);
+        assert_eq!(policy.delay(1, &CandlesError::RateLimited { retry_after: None }), Some(Duration::from_millis(100)));
+
+        // A banned IP is never retried blindly
+        let banned = |secs| CandlesError::IpBanned {
+            retry_after: Some(Duration::from_secs(secs)),
+        };
+        assert_eq!(policy.delay(1, &CandlesError::IpBanned { retry_after: None }), None);
+        assert_eq!(policy.delay(1, &banned(1)), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(1, &banned(60)), None);
+
+        // Bans follow their own switch rather than the retryable statuses
+        let no_429 = RetryPolicy {
+            retryable_statuses: vec![503],
+            ..policy.clone()
+        };
+        assert_eq!(no_429.delay(1, &banned(1)), Some(Duration::from_secs(1)));
+        assert_eq!(no_429.delay(1, &rate_limited(1)), None);
+
+        let no_bans = RetryPolicy { retry_ip_bans: false, ..policy };
+        assert_eq!(no_bans.delay(1, &banned(1)), None);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failure_is_retried() {
+        let server = serve(Mock::given(any()), "binance/spot_klines.json").await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+
+        let config = config(&server).with_retry(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+        let candles = Binance::new(config)
+            .get_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot).into())
+            .await
+            .unwrap();
+
+        assert_candles(&candles, &EXPECTED_CANDLES);
+    }
+}
@@ -0,0 +1,150 @@
+//! Versioned storage format for candles and instruments.
+//!
+//! [`Candle`](crate::types::Candle), [`Instrument`](crate::types::Instrument), [`Timeframe`](crate::types::Timeframe),
+//! [`MarketType`](crate::types::MarketType) and [`Connection`](crate::connections::Connection) serialize with stable
+//! names. Wrapping stored data in [`Versioned`] records the schema it was written with, so later releases can keep
+//! reading it and older ones refuse data they do not understand.
+//!
+//! Version history:
+//! - 1: initial schema
+
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+
+use crate::{errors::CandlesError, types::Number};
+
+/// The schema version written by this release.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u32,
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wraps `data` with the current [`SCHEMA_VERSION`].
+    pub fn new(data: T) -> Self {
+        Self { version: SCHEMA_VERSION, data }
+    }
+
+    /// The payload, or [`CandlesError::UnsupportedSchemaVersion`] when it was written by a newer release.
+    pub fn into_data(self) -> Result<T, CandlesError> {
+        match self.version <= SCHEMA_VERSION {
+            true => Ok(self.data),
+            false => Err(CandlesError::UnsupportedSchemaVersion {
+                version: self.version,
+                supported: SCHEMA_VERSION,
+            }),
+        }
+    }
+}
+
+/// A stored [`Number`] as either a JSON number or a string, so `f64` and `decimal` builds read each other's data.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredNumber {
+    Number(Number),
+    String(String),
+}
+
+impl StoredNumber {
+    fn into_number<E: Error>(self) -> Result<Number, E> {
+        match self {
+            StoredNumber::Number(number) => Ok(number),
+            StoredNumber::String(s) => s.parse().map_err(|_| E::custom(format!("invalid number {s:?}"))),
+        }
+    }
+}
+
+pub(crate) fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Number, D::Error> {
+    StoredNumber::deserialize(deserializer)?.into_number()
+}
+
+pub(crate) fn optional_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Number>, D::Error> {
+    Option::<StoredNumber>::deserialize(deserializer)?.map(StoredNumber::into_number).transpose()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{Value, json};
+
+    use crate::{
+        connections::Connection,
+        errors::CandlesError,
+        schema::{SCHEMA_VERSION, Versioned},
+        testing::{float, instrument},
+        types::{Candle, Instrument, MarketType, Timeframe},
+    };
+
+    /// Version 1 as written to disk. Must keep deserializing in every later release.
+    const STORED_V1: &str = r#"{
+        "version": 1,
+        "data": {
+            "instrument": {"asset_id": "bitcoin", "pair": "BTCUSDT", "connection": "binance", "market_type": "spot", "timeframe": "h1"},
+            "candles": [
+                {"timestamp": 1710000000000, "open": 68000.1, "high": 68500.2, "low": 67800.3, "close": 68200.4, "volume": 123.456},
+                {"timestamp": 1710003600000, "open": "68200.4", "high": "68400", "low": "68100.5", "close": "68300.6", "volume": "98.7",
+                 "quote_volume": 6739203.51, "trades": 8123, "is_closed": true}
+            ]
+        }
+    }"#;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Watchlist {
+        instrument: Instrument,
+        candles: Vec<Candle>,
+    }
+
+    #[test]
+    fn test_reads_stored_v1() {
+        let stored: Versioned<Watchlist> = serde_json::from_str(STORED_V1).unwrap();
+        let watchlist = stored.into_data().unwrap();
+
+        assert_eq!(watchlist.instrument.connection, Connection::Binance);
+        assert_eq!(watchlist.instrument.market_type, MarketType::Spot);
+        assert_eq!(watchlist.instrument.timeframe, Timeframe::H1);
+        assert_eq!(float(watchlist.candles[0].open), 68000.1);
+        assert_eq!(watchlist.candles[0].quote_volume, None);
+        assert!(!watchlist.candles[0].is_closed);
+        assert_eq!(float(watchlist.candles[1].close), 68300.6);
+        assert_eq!(watchlist.candles[1].trades, Some(8123));
+        assert!(watchlist.candles[1].is_closed);
+    }
+
+    #[test]
+    fn test_round_trip_keeps_field_names() {
+        let watchlist = Watchlist {
+            instrument: instrument(Connection::OKX, "BTC-USDT", MarketType::Derivatives),
+            candles: vec![Candle {
+                timestamp: 1710000000000,
+                trades: Some(42),
+                ..Default::default()
+            }],
+        };
+
+        let json = serde_json::to_value(Versioned::new(watchlist)).unwrap();
+
+        assert_eq!(json["version"], SCHEMA_VERSION);
+        assert_eq!(
+            json["data"]["instrument"],
+            json!({"asset_id": "bitcoin", "pair": "BTC-USDT", "connection": "okx", "market_type": "derivatives", "timeframe": "h1"})
+        );
+        let Value::Object(candle) = &json["data"]["candles"][0] else {
+            panic!("candle is not an object")
+        };
+        assert_eq!(
+            candle.keys().collect::<Vec<_>>(),
+            ["close", "high", "is_closed", "low", "open", "timestamp", "trades", "volume"]
+        );
+
+        let read: Versioned<Watchlist> = serde_json::from_value(json).unwrap();
+        assert_eq!(read.into_data().unwrap().candles[0].trades, Some(42));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let stored: Versioned<Vec<Candle>> = serde_json::from_str(r#"{"version": 99, "data": []}"#).unwrap();
+
+        assert!(matches!(stored.into_data(), Err(CandlesError::UnsupportedSchemaVersion { version: 99, .. })));
+    }
+}
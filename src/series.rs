@@ -0,0 +1,193 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::types::{Candle, Instrument, Number};
+
+/// Candles as columns, one vector per field, for vectorised math. Rows share an index across every column.
+///
+/// Timestamps are ascending open times in milliseconds, as returned by every connection.
+#[derive(Debug, Clone)]
+pub struct CandleSeries {
+    pub instrument: Instrument,
+    pub timestamps: Vec<i64>,
+    pub open: Vec<Number>,
+    pub high: Vec<Number>,
+    pub low: Vec<Number>,
+    pub close: Vec<Number>,
+    pub volume: Vec<Number>,
+    pub quote_volume: Vec<Option<Number>>,
+    pub trades: Vec<Option<u64>>,
+    pub taker_buy_volume: Vec<Option<Number>>,
+    pub taker_buy_quote_volume: Vec<Option<Number>>,
+    pub is_closed: Vec<bool>,
+}
+
+impl CandleSeries {
+    pub fn new(instrument: Instrument) -> Self {
+        Self::with_capacity(instrument, 0)
+    }
+
+    pub fn with_capacity(instrument: Instrument, capacity: usize) -> Self {
+        Self {
+            instrument,
+            timestamps: Vec::with_capacity(capacity),
+            open: Vec::with_capacity(capacity),
+            high: Vec::with_capacity(capacity),
+            low: Vec::with_capacity(capacity),
+            close: Vec::with_capacity(capacity),
+            volume: Vec::with_capacity(capacity),
+            quote_volume: Vec::with_capacity(capacity),
+            trades: Vec::with_capacity(capacity),
+            taker_buy_volume: Vec::with_capacity(capacity),
+            taker_buy_quote_volume: Vec::with_capacity(capacity),
+            is_closed: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn from_candles(instrument: Instrument, candles: Vec<Candle>) -> Self {
+        let mut series = Self::with_capacity(instrument, candles.len());
+        candles.into_iter().for_each(|candle| series.push(candle));
+        series
+    }
+
+    pub fn into_candles(self) -> Vec<Candle> {
+        self.iter().collect()
+    }
+
+    pub fn push(&mut self, candle: Candle) {
+        self.timestamps.push(candle.timestamp);
+        self.open.push(candle.open);
+        self.high.push(candle.high);
+        self.low.push(candle.low);
+        self.close.push(candle.close);
+        self.volume.push(candle.volume);
+        self.quote_volume.push(candle.quote_volume);
+        self.trades.push(candle.trades);
+        self.taker_buy_volume.push(candle.taker_buy_volume);
+        self.taker_buy_quote_volume.push(candle.taker_buy_quote_volume);
+        self.is_closed.push(candle.is_closed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// The candle at row `index`.
+    pub fn get(&self, index: usize) -> Option<Candle> {
+        (index < self.len()).then(|| Candle {
+            timestamp: self.timestamps[index],
+            open: self.open[index],
+            high: self.high[index],
+            low: self.low[index],
+            close: self.close[index],
+            volume: self.volume[index],
+            quote_volume: self.quote_volume[index],
+            trades: self.trades[index],
+            taker_buy_volume: self.taker_buy_volume[index],
+            taker_buy_quote_volume: self.taker_buy_quote_volume[index],
+            is_closed: self.is_closed[index],
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Candle> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// The rows whose open time falls in `range` (milliseconds), e.g. `series.slice(start..end)`.
+    pub fn slice(&self, range: impl RangeBounds<i64>) -> CandleSeries {
+        let from = match range.start_bound() {
+            Bound::Included(start) => self.timestamps.partition_point(|t| t < start),
+            Bound::Excluded(start) => self.timestamps.partition_point(|t| t <= start),
+            Bound::Unbounded => 0,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(end) => self.timestamps.partition_point(|t| t <= end),
+            Bound::Excluded(end) => self.timestamps.partition_point(|t| t < end),
+            Bound::Unbounded => self.len(),
+        };
+        let rows = from..to.max(from);
+
+        CandleSeries {
+            instrument: self.instrument.clone(),
+            timestamps: self.timestamps[rows.clone()].to_vec(),
+            open: self.open[rows.clone()].to_vec(),
+            high: self.high[rows.clone()].to_vec(),
+            low: self.low[rows.clone()].to_vec(),
+            close: self.close[rows.clone()].to_vec(),
+            volume: self.volume[rows.clone()].to_vec(),
+            quote_volume: self.quote_volume[rows.clone()].to_vec(),
+            trades: self.trades[rows.clone()].to_vec(),
+            taker_buy_volume: self.taker_buy_volume[rows.clone()].to_vec(),
+            taker_buy_quote_volume: self.taker_buy_quote_volume[rows.clone()].to_vec(),
+            is_closed: self.is_closed[rows].to_vec(),
+        }
+    }
+}
+
+impl IntoIterator for CandleSeries {
+    type Item = Candle;
+    type IntoIter = std::vec::IntoIter<Candle>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_candles().into_iter()
+    }
+}
+
+impl From<CandleSeries> for Vec<Candle> {
+    fn from(series: CandleSeries) -> Self {
+        series.into_candles()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        connections::Connection,
+        series::CandleSeries,
+        testing::{EXPECTED_CANDLES, float, instrument},
+        types::{Candle, MarketType},
+    };
+
+    fn series() -> CandleSeries {
+        let candles = EXPECTED_CANDLES
+            .iter()
+            .map(|&(timestamp, open, high, low, close, volume)| Candle {
+                timestamp,
+                open: open.to_string().parse().unwrap(),
+                high: high.to_string().parse().unwrap(),
+                low: low.to_string().parse().unwrap(),
+                close: close.to_string().parse().unwrap(),
+                volume: volume.to_string().parse().unwrap(),
+                ..Default::default()
+            })
+            .collect();
+
+        CandleSeries::from_candles(instrument(Connection::Binance, "BTCUSDT", MarketType::Spot), candles)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let series = series();
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.timestamps, EXPECTED_CANDLES.map(|c| c.0));
+        assert_eq!(series.close.iter().copied().map(float).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.4));
+
+        let candles = series.into_candles();
+        assert_eq!(candles.iter().map(|c| c.timestamp).collect::<Vec<_>>(), EXPECTED_CANDLES.map(|c| c.0));
+    }
+
+    #[test]
+    fn test_slice_by_time() {
+        let series = series();
+
+        assert_eq!(series.slice(1710003600000..).timestamps, [1710003600000, 1710007200000]);
+        assert_eq!(series.slice(..1710003600000).timestamps, [1710000000000]);
+        assert_eq!(series.slice(1710000000000..=1710003600000).timestamps, [1710000000000, 1710003600000]);
+        assert!(series.slice(1710001000000..1710002000000).is_empty());
+        assert_eq!(series.slice(..).instrument.pair, "BTCUSDT");
+    }
+}
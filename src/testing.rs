@@ -0,0 +1,89 @@
+//! Offline test harness: serves recorded exchange responses from `tests/fixtures` on a local server.
+
+use wiremock::{Mock, MockBuilder, MockServer, ResponseTemplate, matchers::any};
+
+use crate::{
+    config::ClientConfig,
+    connections::Connection,
+    retry::RetryPolicy,
+    types::{Candle, Instrument, MarketType, Number, Timeframe},
+};
+
+/// The candles every fixture encodes, ascending: (timestamp, open, high, low, close, volume)
+pub const EXPECTED_CANDLES: [(i64, f64, f64, f64, f64, f64); 3] = [
+    (1710000000000, 68000.1, 68500.2, 67800.3, 68200.4, 123.456),
+    (1710003600000, 68200.4, 68400.0, 68100.5, 68300.6, 98.7),
+    (1710007200000, 68300.6, 68350.0, 68050.0, 68100.2, 45.25),
+];
+
+/// Mark, index and premium price fixtures, which carry no volume
+pub const EXPECTED_PRICE_CANDLES: [(i64, f64, f64, f64, f64, f64); 3] = [
+    (1710000000000, 68010.5, 68490.1, 67815.2, 68195.3, 0.0),
+    (1710003600000, 68195.3, 68395.8, 68110.7, 68301.2, 0.0),
+    (1710007200000, 68301.2, 68345.4, 68055.9, 68098.6, 0.0),
+];
+
+/// Quote volumes of [`EXPECTED_CANDLES`], for fixtures that report them
+pub const EXPECTED_QUOTE_VOLUMES: [f64; 3] = [8418432.12345678, 6739203.51, 3081463.05];
+
+pub fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read fixture {path}: {e}"))
+}
+
+/// Starts a server answering `mock` exactly once with the fixture `name`.
+pub async fn serve(mock: MockBuilder, name: &str) -> MockServer {
+    let server = MockServer::start().await;
+
+    mock.respond_with(ResponseTemplate::new(200).set_body_raw(fixture(name), "application/json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    server
+}
+
+/// Starts a server answering every request with `status` and a raw `body`.
+pub async fn serve_status(status: u16, body: &str) -> MockServer {
+    let server = MockServer::start().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(status).set_body_raw(body.to_owned(), "application/json"))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+/// Points a client at `server`, with retries off so error responses surface immediately.
+pub fn config(server: &MockServer) -> ClientConfig {
+    ClientConfig::default().with_base_url(server.uri()).with_retry(RetryPolicy::disabled())
+}
+
+pub fn instrument(connection: Connection, pair: &str, market_type: MarketType) -> Instrument {
+    Instrument {
+        asset_id: "bitcoin".to_owned(),
+        pair: pair.to_owned(),
+        connection,
+        market_type,
+        timeframe: Timeframe::H1,
+    }
+}
+
+pub fn assert_candles(candles: &[Candle], expected: &[(i64, f64, f64, f64, f64, f64)]) {
+    let actual: Vec<_> = candles
+        .iter()
+        .map(|c| (c.timestamp, float(c.open), float(c.high), float(c.low), float(c.close), float(c.volume)))
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+pub fn assert_quote_volumes(candles: &[Candle]) {
+    let actual: Vec<_> = candles.iter().map(|c| c.quote_volume.map(float)).collect();
+    assert_eq!(actual, EXPECTED_QUOTE_VOLUMES.map(Some));
+}
+
+/// Compares candles as `f64` whichever [`Number`] is enabled.
+pub fn float(number: Number) -> f64 {
+    number.to_string().parse().unwrap()
+}
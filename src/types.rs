@@ -1,33 +1,123 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
-use strum::{Display, EnumString};
+use strum::{Display, EnumIter, EnumString};
 
-use crate::connections::Connection;
+use crate::{connections::Connection, delivery::DeliveryContract, options::OptionContract};
 
-#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Display, EnumString)]
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Display, EnumString, EnumIter)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 pub enum MarketType {
     #[default]
     Spot,
+    /// Stablecoin (USDT) margined perpetuals
     Derivatives,
+    /// Coin-margined perpetuals, quoted in USD contracts and settled in the base coin
+    Inverse,
+    /// Dated futures that settle at expiry. The symbol names the expiry and whether they are coin-margined, see
+    /// [`crate::delivery::DeliveryContract`]
+    Delivery,
+    /// Options, with expiry, strike and call or put in the symbol, see [`crate::options::OptionContract`]
+    Options,
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Display, EnumString, Clone, Serialize, Deserialize)]
+#[derive(Debug, Hash, Eq, PartialEq, Display, EnumString, EnumIter, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 pub enum Timeframe {
+    S1,
+    M1,
     M3,
     M5,
     M15,
     M30,
     H1,
+    H2,
     H4,
+    H6,
+    H8,
+    H12,
     D1,
+    D3,
     W1,
     MN1,
 }
 
-#[derive(Debug, Clone)]
+impl Timeframe {
+    /// Length of one candle, `None` for months, whose length follows the calendar; see [`Timeframe::close_time`].
+    pub fn duration(&self) -> Option<Duration> {
+        let seconds = match self {
+            Timeframe::S1 => 1,
+            Timeframe::M1 => 60,
+            Timeframe::M3 => 3 * 60,
+            Timeframe::M5 => 5 * 60,
+            Timeframe::M15 => 15 * 60,
+            Timeframe::M30 => 30 * 60,
+            Timeframe::H1 => 60 * 60,
+            Timeframe::H2 => 2 * 60 * 60,
+            Timeframe::H4 => 4 * 60 * 60,
+            Timeframe::H6 => 6 * 60 * 60,
+            Timeframe::H8 => 8 * 60 * 60,
+            Timeframe::H12 => 12 * 60 * 60,
+            Timeframe::D1 => 24 * 60 * 60,
+            Timeframe::D3 => 3 * 24 * 60 * 60,
+            Timeframe::W1 => 7 * 24 * 60 * 60,
+            Timeframe::MN1 => return None,
+        };
+
+        Some(Duration::seconds(seconds))
+    }
+
+    /// Open time of the candle containing `timestamp`, in milliseconds. Weeks start on Monday, months on the 1st, anything
+    /// else on a multiple of its length since the Unix epoch, all in UTC.
+    pub fn floor(&self, timestamp: i64) -> i64 {
+        match self.duration() {
+            Some(duration) => timestamp - (timestamp - self.origin()).rem_euclid(duration.num_milliseconds()),
+            None => DateTime::from_timestamp_millis(timestamp)
+                .and_then(|time| time.date_naive().with_day(1))
+                .map_or(timestamp, |day| day.and_time(NaiveTime::MIN).and_utc().timestamp_millis()),
+        }
+    }
+
+    /// Close time of the candle opened at `open`, in milliseconds, which is also the next candle's open.
+    pub fn close_time(&self, open: i64) -> i64 {
+        match self.duration() {
+            Some(duration) => open + duration.num_milliseconds(),
+            None => DateTime::from_timestamp_millis(open)
+                .and_then(|open| open.checked_add_months(Months::new(1)))
+                .map_or(i64::MAX, |close| close.timestamp_millis()),
+        }
+    }
+
+    /// Close time of the candle containing `timestamp`, in milliseconds.
+    pub fn next_close(&self, timestamp: i64) -> i64 {
+        self.close_time(self.floor(timestamp))
+    }
+
+    /// Number of candles opening between `start` and `end` inclusive, in milliseconds.
+    pub fn count(&self, start: i64, end: i64) -> u64 {
+        (self.index(end) - self.index(start - 1)).max(0) as u64
+    }
+
+    /// Position of the candle containing `timestamp`, counted from an arbitrary origin.
+    fn index(&self, timestamp: i64) -> i64 {
+        match self.duration() {
+            Some(duration) => (timestamp - self.origin()).div_euclid(duration.num_milliseconds()),
+            None => DateTime::from_timestamp_millis(timestamp).map_or(0, |time| time.year() as i64 * 12 + time.month0() as i64),
+        }
+    }
+
+    /// A candle open to align fixed lengths to: the Unix epoch, or for weeks the first Monday after it (1970-01-05).
+    fn origin(&self) -> i64 {
+        match self {
+            Timeframe::W1 => 4 * 24 * 60 * 60 * 1000,
+            _ => 0,
+        }
+    }
+}
+
+/// Serialized field names are part of the stored schema, see [`crate::schema`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Instrument {
     pub asset_id: String,
     pub pair: String,
@@ -36,12 +126,210 @@ pub struct Instrument {
     pub timeframe: Timeframe,
 }
 
-#[derive(Debug, Serialize, Clone)]
+impl Instrument {
+    /// The dated future this instrument refers to, `None` unless it is a [`MarketType::Delivery`] symbol.
+    pub fn delivery_contract(&self) -> Option<DeliveryContract> {
+        match self.market_type {
+            MarketType::Delivery => DeliveryContract::parse(&self.connection, &self.pair),
+            _ => None,
+        }
+    }
+
+    /// Expiry, strike and call or put of this instrument, `None` unless it is a [`MarketType::Options`] symbol.
+    pub fn option_contract(&self) -> Option<OptionContract> {
+        match self.market_type {
+            MarketType::Options => OptionContract::parse(&self.connection, &self.pair),
+            _ => None,
+        }
+    }
+}
+
+/// Which price the candles follow.
+#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Default, Clone, Copy, Display, EnumString, EnumIter)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PriceSource {
+    /// Traded prices, with volumes
+    #[default]
+    Last,
+    /// The exchange's mark price, used for margin and liquidation
+    Mark,
+    /// The spot index a contract tracks
+    Index,
+    /// The premium of a perpetual over its index, which funding is derived from
+    Premium,
+}
+
+/// A candles query: the instrument plus an optional time range and result limit.
+///
+/// `start` and `end` are inclusive bounds on the candle open time, in milliseconds since the Unix epoch.
+/// When no range is set the exchange returns its latest candles. `limit` caps the number of candles, keeping the most
+/// recent ones; without it each exchange returns its default page. `closed_only` drops the candle still forming.
+/// `price_source` selects mark, index or premium candles instead of traded ones; those carry no volume.
+#[derive(Debug, Clone)]
+pub struct CandlesRequest {
+    pub instrument: Instrument,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub limit: Option<usize>,
+    pub closed_only: bool,
+    pub price_source: PriceSource,
+}
+
+impl CandlesRequest {
+    pub fn new(instrument: Instrument) -> Self {
+        Self {
+            instrument,
+            start: None,
+            end: None,
+            limit: None,
+            closed_only: false,
+            price_source: PriceSource::Last,
+        }
+    }
+
+    pub fn with_start(mut self, start: i64) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn with_end(mut self, end: i64) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_closed_only(mut self, closed_only: bool) -> Self {
+        self.closed_only = closed_only;
+        self
+    }
+
+    pub fn with_price_source(mut self, price_source: PriceSource) -> Self {
+        self.price_source = price_source;
+        self
+    }
+
+    pub fn with_range(self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.with_start(start.timestamp_millis()).with_end(end.timestamp_millis())
+    }
+
+    pub fn has_range(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    /// Whether a candle opened at `timestamp` (milliseconds) falls inside the requested range.
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.start.is_none_or(|start| timestamp >= start) && self.end.is_none_or(|end| timestamp <= end)
+    }
+}
+
+impl From<Instrument> for CandlesRequest {
+    fn from(instrument: Instrument) -> Self {
+        Self::new(instrument)
+    }
+}
+
+/// Numeric type of candle prices and volumes. `f64` by default, `rust_decimal::Decimal` with the `decimal` feature,
+/// which keeps string-encoded values exactly as the exchange sent them.
+#[cfg(not(feature = "decimal"))]
+pub type Number = f64;
+#[cfg(feature = "decimal")]
+pub type Number = rust_decimal::Decimal;
+
+/// Earliest accepted candle open time, the Bitcoin genesis block (2009-01-03). Anything below is a unit mix-up, e.g. seconds.
+pub const MIN_CANDLE_TIMESTAMP: i64 = 1_230_940_800_000;
+
+/// Serialized field names are part of the stored schema, see [`crate::schema`]. Fields added later default when missing.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Candle {
-    pub timestamp: i64,
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64, // Volume in base asset (BTC for BTC/USDT)
+    pub timestamp: i64, // Open time in milliseconds since the Unix epoch, for every connection
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub open: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub high: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub low: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub close: Number,
+    #[serde(deserialize_with = "crate::schema::number")]
+    pub volume: Number, // Volume in base asset (BTC for BTC/USDT)
+
+    // Extended fields, only set where the exchange reports them
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "crate::schema::optional_number")]
+    pub quote_volume: Option<Number>, // Volume in quote asset (USDT for BTC/USDT)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trades: Option<u64>, // Number of trades
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "crate::schema::optional_number")]
+    pub taker_buy_volume: Option<Number>, // Taker buy volume in base asset
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "crate::schema::optional_number")]
+    pub taker_buy_quote_volume: Option<Number>, // Taker buy volume in quote asset
+
+    #[serde(default)]
+    pub is_closed: bool, // Final, as confirmed by the exchange or by the close time having passed
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::types::Timeframe;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().timestamp_millis()
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(Timeframe::M15.duration(), Some(Duration::minutes(15)));
+        assert_eq!(Timeframe::D3.duration(), Some(Duration::days(3)));
+        assert_eq!(Timeframe::MN1.duration(), None);
+    }
+
+    #[test]
+    fn test_floor() {
+        assert_eq!(Timeframe::M15.floor(at(2024, 3, 6, 10, 44)), at(2024, 3, 6, 10, 30));
+        assert_eq!(Timeframe::H4.floor(at(2024, 3, 6, 10, 44)), at(2024, 3, 6, 8, 0));
+        assert_eq!(Timeframe::H4.floor(at(2024, 3, 6, 8, 0)), at(2024, 3, 6, 8, 0));
+        // Wednesday and Sunday both belong to the week of Monday 2024-03-04
+        assert_eq!(Timeframe::W1.floor(at(2024, 3, 6, 10, 44)), at(2024, 3, 4, 0, 0));
+        assert_eq!(Timeframe::W1.floor(at(2024, 3, 10, 23, 59)), at(2024, 3, 4, 0, 0));
+        assert_eq!(Timeframe::MN1.floor(at(2024, 2, 29, 23, 59)), at(2024, 2, 1, 0, 0));
+        // Before the epoch
+        assert_eq!(Timeframe::D1.floor(-1), -24 * 3600 * 1000);
+    }
+
+    #[test]
+    fn test_next_close() {
+        assert_eq!(Timeframe::H1.next_close(at(2024, 3, 6, 10, 44)), at(2024, 3, 6, 11, 0));
+        assert_eq!(Timeframe::H1.next_close(at(2024, 3, 6, 10, 0)), at(2024, 3, 6, 11, 0));
+        assert_eq!(Timeframe::W1.next_close(at(2024, 3, 6, 10, 44)), at(2024, 3, 11, 0, 0));
+        assert_eq!(Timeframe::MN1.next_close(at(2023, 12, 15, 0, 0)), at(2024, 1, 1, 0, 0));
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(Timeframe::H1.count(at(2024, 3, 6, 0, 0), at(2024, 3, 6, 23, 0)), 24);
+        // Only opens inside the range count
+        assert_eq!(Timeframe::H1.count(at(2024, 3, 6, 0, 30), at(2024, 3, 6, 2, 30)), 2);
+        assert_eq!(Timeframe::H1.count(at(2024, 3, 6, 0, 30), at(2024, 3, 6, 0, 45)), 0);
+        assert_eq!(Timeframe::H1.count(at(2024, 3, 6, 2, 0), at(2024, 3, 6, 1, 0)), 0);
+        // Mondays 03-04, 03-11 and 03-18
+        assert_eq!(Timeframe::W1.count(at(2024, 3, 1, 0, 0), at(2024, 3, 20, 0, 0)), 3);
+        assert_eq!(Timeframe::MN1.count(at(2023, 11, 1, 0, 0), at(2024, 2, 29, 0, 0)), 4);
+        assert_eq!(Timeframe::MN1.count(at(2023, 11, 2, 0, 0), at(2024, 2, 29, 0, 0)), 3);
+    }
+
+    #[test]
+    fn test_close_time() {
+        let open = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap().timestamp_millis();
+
+        assert_eq!(Timeframe::H4.close_time(open), open + 4 * 3600 * 1000);
+        assert_eq!(Timeframe::W1.close_time(open), open + 7 * 24 * 3600 * 1000);
+        // February 2024 has 29 days
+        assert_eq!(Timeframe::MN1.close_time(open), Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap().timestamp_millis());
+    }
 }
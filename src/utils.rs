@@ -1,5 +1,9 @@
-use crate::{errors::CandlesError, types::Candle};
-use chrono::{DateTime, Duration};
+use crate::{
+    connections::Connection,
+    errors::CandlesError,
+    types::{Candle, MIN_CANDLE_TIMESTAMP, Number, Timeframe},
+};
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
 use serde_json::Value;
 
@@ -13,36 +17,233 @@ pub struct ResultWrapper<T> {
     pub result: T,
 }
 
+/// `{"code", "msg", "data"}` envelope used by OKX, BloFin and BingX
 #[derive(Deserialize, Debug)]
 pub struct DataWrapperWithMsgCode<C, T> {
     pub code: C,
     pub msg: Option<String>,
-    pub data: T,
+    pub data: Option<T>,
+}
+
+impl<C: ToString, T> DataWrapperWithMsgCode<C, T> {
+    /// The payload when `code` is `success`, otherwise the exchange's error.
+    pub fn into_data(self, success: &str) -> Result<T, CandlesError> {
+        check_envelope(self.code.to_string(), success, self.msg, self.data)
+    }
 }
 
+/// `{"code", "message", "data"}` envelope used by Mexc contracts
 #[derive(Deserialize, Debug)]
 pub struct DataWrapperWithStatusCode<C, T> {
     pub code: C,
     pub message: Option<String>,
-    pub data: T,
+    pub data: Option<T>,
+}
+
+impl<C: ToString, T> DataWrapperWithStatusCode<C, T> {
+    /// The payload when `code` is `success`, otherwise the exchange's error.
+    pub fn into_data(self, success: &str) -> Result<T, CandlesError> {
+        check_envelope(self.code.to_string(), success, self.message, self.data)
+    }
+}
+
+/// `{"retCode", "retMsg", "result"}` envelope used by Bybit
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultWrapperWithRetCode<T> {
+    pub ret_code: i64,
+    pub ret_msg: Option<String>,
+    pub result: Option<T>,
+}
+
+impl<T> ResultWrapperWithRetCode<T> {
+    /// The payload when `retCode` is 0, otherwise the exchange's error.
+    pub fn into_result(self) -> Result<T, CandlesError> {
+        check_envelope(self.ret_code.to_string(), "0", self.ret_msg, self.result)
+    }
+}
+
+/// `{"status", "err-code", "err-msg", "data"}` envelope used by HTX. Derivatives spell the error keys with underscores.
+#[derive(Deserialize, Debug)]
+pub struct DataWrapperWithStatus<T> {
+    pub status: String,
+    #[serde(rename = "err-code", alias = "err_code")]
+    pub err_code: Option<Value>,
+    #[serde(rename = "err-msg", alias = "err_msg")]
+    pub err_msg: Option<String>,
+    pub data: Option<T>,
+}
+
+impl<T> DataWrapperWithStatus<T> {
+    /// The payload when `status` is "ok", otherwise the exchange's error.
+    pub fn into_data(self) -> Result<T, CandlesError> {
+        let code = match self.err_code {
+            _ if self.status == "ok" => self.status,
+            Some(Value::String(code)) => code,
+            Some(code) => code.to_string(),
+            None => self.status,
+        };
+
+        check_envelope(code, "ok", self.err_msg, self.data)
+    }
 }
 
-pub fn parse_string_to_f64(val: &Value, field: &str, index: usize) -> Result<f64, CandlesError> {
+fn check_envelope<T>(code: String, success: &str, message: Option<String>, data: Option<T>) -> Result<T, CandlesError> {
+    if code != success {
+        return Err(CandlesError::ExchangeError {
+            code,
+            message: message.unwrap_or_default(),
+        });
+    }
+
+    data.ok_or_else(|| CandlesError::parse("data", 0, "missing payload"))
+}
+
+/// Turns an [`CandlesError::ExchangeError`] the exchange uses for unknown symbols into [`CandlesError::SymbolNotFound`].
+pub fn map_symbol_not_found(error: CandlesError, connection: Connection, symbol: &str, is_symbol_not_found: impl Fn(&str, &str) -> bool) -> CandlesError {
+    match &error {
+        CandlesError::ExchangeError { code, message } if is_symbol_not_found(code, message) => CandlesError::SymbolNotFound {
+            connection,
+            symbol: symbol.to_string(),
+        },
+        _ => error,
+    }
+}
+
+/// Error body of Binance-style APIs, e.g. `{"code": -1121, "msg": "Invalid symbol."}`
+#[derive(Deserialize, Debug)]
+pub struct MsgCode<C> {
+    pub code: C,
+    pub msg: String,
+}
+
+/// Maps the `{"code", "msg"}` body of a rejected Binance-style request to a typed error.
+pub fn map_msg_code_error(error: CandlesError, connection: Connection, symbol: &str, symbol_not_found_code: i64) -> CandlesError {
+    let CandlesError::HttpStatus { body, .. } = &error else {
+        return error;
+    };
+
+    match serde_json::from_str::<MsgCode<i64>>(body) {
+        Ok(MsgCode { code, .. }) if code == symbol_not_found_code => CandlesError::SymbolNotFound {
+            connection,
+            symbol: symbol.to_string(),
+        },
+        Ok(MsgCode { code, msg }) => CandlesError::ExchangeError {
+            code: code.to_string(),
+            message: msg,
+        },
+        Err(_) => error,
+    }
+}
+
+pub fn parse_number(val: &Value, field: &str, index: usize) -> Result<Number, CandlesError> {
     match val {
-        // Handle string values like "4524.78"
-        Value::String(s) => s.parse().map_err(|_| CandlesError::Other(format!("Failed to parse {field} at index {index}: {val}"))),
-        // Handle number values like 4524.78
-        Value::Number(n) => n
-            .as_f64()
-            .ok_or_else(|| CandlesError::Other(format!("Failed to convert {field} to f64 at index {index}: {val}"))),
+        // Handle string values like "4524.78", exact with the `decimal` feature
+        Value::String(s) => number_from_str(s).ok_or_else(|| CandlesError::parse(field, index, val)),
+        // Handle number values like 4524.78, already rounded to f64 by the JSON parser
+        Value::Number(n) => n.as_f64().and_then(number_from_f64).ok_or_else(|| CandlesError::parse(field, index, val)),
         // Handle any other type
-        _ => Err(CandlesError::Other(format!("Invalid {field} type at index {index}: expected string or number, got {val}"))),
+        _ => Err(CandlesError::parse(field, index, val)),
     }
 }
 
-pub fn examine_candles(candles: &[Candle]) {
-    use chrono::Utc;
+#[cfg(not(feature = "decimal"))]
+fn number_from_str(s: &str) -> Option<Number> {
+    s.parse().ok()
+}
+
+#[cfg(feature = "decimal")]
+fn number_from_str(s: &str) -> Option<Number> {
+    use std::str::FromStr;
+
+    Number::from_str(s).or_else(|_| Number::from_scientific(s)).ok()
+}
+
+#[cfg(not(feature = "decimal"))]
+fn number_from_f64(n: f64) -> Option<Number> {
+    Some(n)
+}
+
+/// Goes through the shortest representation that round-trips, so `0.1` becomes exactly `0.1`.
+#[cfg(feature = "decimal")]
+fn number_from_f64(n: f64) -> Option<Number> {
+    number_from_str(&n.to_string())
+}
+
+pub fn parse_timestamp(val: &Value, index: usize) -> Result<i64, CandlesError> {
+    match val {
+        // Handle number values like 1710000000000
+        Value::Number(n) => n.as_i64().ok_or_else(|| CandlesError::parse("timestamp", index, val)),
+        // Handle string values like "1710000000000"
+        Value::String(s) => s.parse().map_err(|_| CandlesError::parse("timestamp", index, val)),
+        // Handle any other type
+        _ => Err(CandlesError::parse("timestamp", index, val)),
+    }
+}
+
+/// Parses an optional trailing field, `None` when the exchange omits it.
+pub fn parse_optional_number(val: Option<&Value>, field: &str, index: usize) -> Result<Option<Number>, CandlesError> {
+    val.map(|val| parse_number(val, field, index)).transpose()
+}
+
+pub fn parse_count(val: &Value, field: &str, index: usize) -> Result<u64, CandlesError> {
+    match val {
+        // Handle number values like 10234
+        Value::Number(n) => n.as_u64().ok_or_else(|| CandlesError::parse(field, index, val)),
+        // Handle string values like "10234"
+        Value::String(s) => s.parse().map_err(|_| CandlesError::parse(field, index, val)),
+        // Handle any other type
+        _ => Err(CandlesError::parse(field, index, val)),
+    }
+}
+
+/// Checks that a candle is an array with at least `min_len` elements.
+pub fn candle_array(val: &Value, index: usize, min_len: usize) -> Result<&Vec<Value>, CandlesError> {
+    match val.as_array() {
+        Some(array) if array.len() >= min_len => Ok(array),
+        _ => Err(CandlesError::parse("candle", index, val)),
+    }
+}
+
+/// A mark, index or premium candle sent as `[open time, open, high, low, close, ...]`. It has no volume.
+pub fn price_candle(val: &Value, index: usize) -> Result<Candle, CandlesError> {
+    let candle_array = candle_array(val, index, 5)?;
+
+    Ok(Candle {
+        timestamp: parse_timestamp(&candle_array[0], index)?,
+        open: parse_number(&candle_array[1], "open price", index)?,
+        high: parse_number(&candle_array[2], "high price", index)?,
+        low: parse_number(&candle_array[3], "low price", index)?,
+        close: parse_number(&candle_array[4], "close price", index)?,
+        ..Default::default()
+    })
+}
 
+/// Rejects timestamps that cannot be millisecond open times: before [`MIN_CANDLE_TIMESTAMP`] or more than a day ahead.
+pub fn validate_timestamps(candles: &[Candle]) -> Result<(), CandlesError> {
+    validate_open_times(candles.iter().map(|c| c.timestamp))
+}
+
+/// [`validate_timestamps`] over a timestamp column.
+pub fn validate_open_times(timestamps: impl IntoIterator<Item = i64>) -> Result<(), CandlesError> {
+    let max = (Utc::now() + Duration::days(1)).timestamp_millis();
+
+    match timestamps.into_iter().enumerate().find(|(_, timestamp)| !(MIN_CANDLE_TIMESTAMP..=max).contains(timestamp)) {
+        Some((index, timestamp)) => Err(CandlesError::parse("timestamp", index, format!("{timestamp} is not a millisecond timestamp"))),
+        None => Ok(()),
+    }
+}
+
+/// Sets [`Candle::is_closed`] from the close time, for exchanges that do not report it.
+pub fn mark_closed(candles: &mut [Candle], timeframe: &Timeframe) {
+    let now = Utc::now().timestamp_millis();
+
+    for candle in candles {
+        candle.is_closed = timeframe.close_time(candle.timestamp) <= now;
+    }
+}
+
+pub fn examine_candles(candles: &[Candle]) {
     assert!(!candles.is_empty(), "Candles array is empty");
     assert!(candles.len() >= 5, "Candles length is < 5");
 
@@ -83,8 +284,37 @@ pub fn examine_candles(candles: &[Candle]) {
     assert!(candle.high >= candle.low, "High ({}) should be >= low ({})", candle.high, candle.low);
 
     // Check close exists and is valid
-    assert!(candle.close > 0.0, "Close price {} should be positive", candle.close);
+    assert!(candle.close > Number::default(), "Close price {} should be positive", candle.close);
 
     // Check volume exists
-    assert!(candle.volume >= 0.0, "Volume {} should be non-negative", candle.volume);
+    assert!(candle.volume >= Number::default(), "Volume {} should be non-negative", candle.volume);
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::utils::parse_number;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number(&json!("68000.1"), "open price", 0).unwrap().to_string(), "68000.1");
+        assert_eq!(parse_number(&json!(0.1), "open price", 0).unwrap().to_string(), "0.1");
+        assert!(parse_number(&json!("n/a"), "open price", 3).is_err());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_parse_number_keeps_exact_decimal() {
+        use std::str::FromStr;
+
+        use rust_decimal::Decimal;
+
+        let parsed = parse_number(&json!("0.000012345678"), "open price", 0).unwrap();
+
+        assert_eq!(parsed, Decimal::from_str("0.000012345678").unwrap());
+        // Trailing zeros are kept as sent
+        assert_eq!(parse_number(&json!("68000.10000000"), "open price", 0).unwrap().to_string(), "68000.10000000");
+        assert_eq!(parse_number(&json!("1.5e-7"), "open price", 0).unwrap(), Decimal::from_str("0.00000015").unwrap());
+    }
 }